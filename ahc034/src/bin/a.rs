@@ -2,10 +2,12 @@
 
 use proconio::fastout;
 use proconio::input;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::time::{Duration, Instant};
 
-#[allow(unused)]
 const TURN_MAX: usize = 100_000;
-const N: usize = 20;
+const RUN_TIME_LIMIT_MS: u64 = if cfg!(debug_assertions) { 100 } else { 1800 };
 
 #[derive(Debug, Clone, Copy)]
 enum Operation {
@@ -32,7 +34,17 @@ impl std::fmt::Display for Operation {
     }
 }
 
+/// `Board` の巻き戻し地点
+#[derive(Debug, Clone, Copy)]
+struct Checkpoint {
+    /// 巻き戻し地点までの操作回数
+    turn: usize,
+    /// 巻き戻し地点でのコスト
+    cost: usize,
+}
+
 struct Board {
+    n: usize,
     load: usize,
     hnn: Vec<Vec<isize>>,
     pos: (usize, usize),
@@ -42,6 +54,19 @@ struct Board {
 }
 
 impl Board {
+    fn new(hnn: Vec<Vec<isize>>) -> Self {
+        let cleared = hnn.iter().flatten().filter(|&&v| v == 0).count();
+        Self {
+            n: hnn.len(),
+            load: 0,
+            hnn,
+            pos: (0, 0),
+            cleared,
+            cost: 0,
+            operations: vec![],
+        }
+    }
+
     #[allow(unused)]
     const DIR: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
 
@@ -65,6 +90,40 @@ impl Board {
         }
     }
 
+    /// 現在位置から `routes` の順に移動し, 経路上のマスを平らにする.
+    /// 経路の最初と最後のマスは平らにせず, 必要な積荷は最初のマスで前借りし,
+    /// 余った積荷は最後のマスに下ろす.
+    fn sweep(&mut self, routes: &[(usize, usize)]) {
+        if routes.is_empty() {
+            return;
+        }
+
+        // 往復の積載量計算, 同じマスを再訪する場合は初回のみ数える
+        let mut h_total = 0;
+        let mut h_total_min = 0;
+        for (idx, &(r, c)) in routes[..routes.len() - 1].iter().enumerate() {
+            if routes[..idx].contains(&(r, c)) {
+                continue;
+            }
+            h_total += self.hnn[r][c];
+            h_total_min = h_total_min.min(h_total);
+        }
+
+        for (idx, &(r_nxt, c_nxt)) in routes.iter().enumerate() {
+            if idx == 0 {
+                if h_total_min < 0 {
+                    self.manual_pop(h_total_min.unsigned_abs());
+                }
+            } else {
+                self.flatten();
+            }
+            self.move_to((r_nxt, c_nxt));
+        }
+        if self.load > 0 {
+            self.manual_push(self.load);
+        }
+    }
+
     /// ダンプカーに積み込む, 現在位置に対しての pop
     fn manual_pop(&mut self, amount: usize) {
         self.work(Operation::Pop(amount));
@@ -90,28 +149,83 @@ impl Board {
         }
     }
 
+    /// 現在の状態を巻き戻し地点として返す
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            turn: self.operations.len(),
+            cost: self.cost,
+        }
+    }
+
+    /// 巻き戻し地点まで操作を取り消す
+    fn rollback(&mut self, checkpoint: Checkpoint) {
+        while self.operations.len() > checkpoint.turn {
+            self.undo();
+        }
+        debug_assert_eq!(self.cost, checkpoint.cost);
+    }
+
+    /// 最後の操作を取り消す.
+    /// 操作はすべて可逆なので, 操作列そのものを undo 用のスタックとして使う.
+    fn undo(&mut self) -> Option<Operation> {
+        let op = self.operations.pop()?;
+        match op {
+            Operation::Pop(x) => {
+                let (i, j) = self.pos;
+                self.update_height(i, j, x as isize);
+                self.load -= x;
+                self.cost -= x;
+            }
+            Operation::Push(x) => {
+                let (i, j) = self.pos;
+                self.update_height(i, j, -(x as isize));
+                self.load += x;
+                self.cost -= x;
+            }
+            Operation::Up => {
+                self.pos.0 += 1;
+                self.cost -= 100 + self.load;
+            }
+            Operation::Down => {
+                self.pos.0 -= 1;
+                self.cost -= 100 + self.load;
+            }
+            Operation::Left => {
+                self.pos.1 += 1;
+                self.cost -= 100 + self.load;
+            }
+            Operation::Right => {
+                self.pos.1 -= 1;
+                self.cost -= 100 + self.load;
+            }
+        }
+
+        Some(op)
+    }
+
+    /// マス (i, j) の高さを `diff` だけ変え, 平らなマスの数を更新する
+    fn update_height(&mut self, i: usize, j: usize, diff: isize) {
+        if self.hnn[i][j] == 0 {
+            self.cleared -= 1;
+        }
+        self.hnn[i][j] += diff;
+        if self.hnn[i][j] == 0 {
+            self.cleared += 1;
+        }
+    }
+
     fn work(&mut self, op: Operation) {
         self.operations.push(op);
         match op {
             Operation::Pop(x) => {
                 let (i, j) = self.pos;
-                if self.hnn[i][j] == x as isize {
-                    self.cleared += 1;
-                } else if self.hnn[i][j] == 0 {
-                    self.cleared -= 1;
-                }
-                self.hnn[i][j] -= x as isize;
+                self.update_height(i, j, -(x as isize));
                 self.load += x;
                 self.cost += x;
             }
             Operation::Push(x) => {
                 let (i, j) = self.pos;
-                if self.hnn[i][j] == -(x as isize) {
-                    self.cleared += 1;
-                } else if self.hnn[i][j] == 0 {
-                    self.cleared -= 1;
-                }
-                self.hnn[i][j] += x as isize;
+                self.update_height(i, j, x as isize);
                 self.load -= x;
                 self.cost += x;
             }
@@ -135,30 +249,46 @@ impl Board {
     }
 }
 
-#[fastout]
-fn main() {
-    input! {
-        _n: usize,
-        hnn: [[isize; N]; N],
-    }
+/// 左右往復の 1 区間.
+/// 分割位置の列から行 `rows.0` を `cols_out` の順に進み, 行 `rows.1` を `cols_back` の順に戻る.
+/// `swapped` なら先に行 `rows.1` へ移って逆の順に往復し, 最後に行 `rows.1` へ戻る.
+struct RowSweep {
+    rows: (usize, usize),
+    cols_out: Vec<usize>,
+    cols_back: Vec<usize>,
+    /// 往復後に移動する行
+    row_next: Option<usize>,
+    swapped: bool,
+}
 
-    let mut cleared = 0;
-    for r in &hnn {
-        for &v in r {
-            if v == 0 {
-                cleared += 1;
-            }
+impl RowSweep {
+    fn run(&self, board: &mut Board, storage_j: usize) {
+        let (row_a, row_b) = self.rows;
+        let routes_of = |row_out: usize, row_back: usize| -> Vec<(usize, usize)> {
+            self.cols_out
+                .iter()
+                .map(|&c| (row_out, c))
+                .chain(self.cols_back.iter().map(|&c| (row_back, c)))
+                .collect()
+        };
+
+        if self.swapped {
+            board.move_to((row_b, storage_j));
+            board.sweep(&routes_of(row_b, row_a));
+            board.move_to((row_b, storage_j));
+        } else {
+            board.sweep(&routes_of(row_a, row_b));
+        }
+
+        if let Some(row_next) = self.row_next {
+            board.move_to((row_next, storage_j));
         }
     }
+}
 
-    let mut board = Board {
-        load: 0,
-        hnn,
-        pos: (0, 0),
-        cleared,
-        cost: 0,
-        operations: vec![],
-    };
+/// 左右往復のルールベースで全マスを平らにする
+fn solve_rule_based(hnn: &[Vec<isize>]) -> Board {
+    let mut board = Board::new(hnn.to_vec());
 
     // 解説放送のルールベースを手実装
 
@@ -167,104 +297,90 @@ fn main() {
     // 分割位置到達時に積荷をすべて下ろす
     // 最後に左下 -> 左上で辻褄をあわせる
 
-    let storage_j = N / 2;
+    let n = board.n;
+    let storage_j = n / 2;
+    // 2 行ずつ往復する. 行数が奇数なら最終行は同じ行を往復する
+    let row_pairs: Vec<(usize, usize)> = (0..n.div_ceil(2))
+        .map(|i| (2 * i, (2 * i + 1).min(n - 1)))
+        .collect();
+
+    let mut sweeps = vec![];
+    // 左半分
+    for (pair_idx, &(top, bottom)) in row_pairs.iter().enumerate() {
+        // 右 -> 左, 降りて左 -> 右. 同じ行を往復する場合は折り返す
+        let return_begin = if top == bottom { 1 } else { 0 };
+        sweeps.push(RowSweep {
+            rows: (top, bottom),
+            cols_out: (0..storage_j).rev().collect(),
+            cols_back: (return_begin..=storage_j).collect(),
+            // 最終行でなければ, 降りる
+            row_next: (pair_idx + 1 < row_pairs.len()).then_some(bottom + 1),
+            swapped: false,
+        });
+    }
+    // 右半分
+    for &(top, bottom) in row_pairs.iter().rev() {
+        // 左 -> 右, 上がって右 -> 左. 同じ行を往復する場合は折り返す
+        let return_begin = if top == bottom { n - 1 } else { n };
+        sweeps.push(RowSweep {
+            rows: (bottom, top),
+            cols_out: (storage_j + 1..n).collect(),
+            cols_back: (storage_j..return_begin).rev().collect(),
+            // 最終行 (行 0) でなければ, 上がる
+            row_next: top.checked_sub(1),
+            swapped: false,
+        });
+    }
 
     // 始点まで動く
     for _ in 0..storage_j {
         board.work(Operation::Right);
     }
 
-    // 左半分
-    for i in 0..N / 2 {
-        // 往復の積載量計算
-        let mut h_total = 0;
-        let mut h_total_min = 0;
-        for j in (0..storage_j).rev() {
-            h_total += board.hnn[2 * i][j];
-            h_total_min = h_total_min.min(h_total);
-        }
-        for j in 0..storage_j {
-            h_total += board.hnn[2 * i + 1][j];
-            h_total_min = h_total_min.min(h_total);
-        }
-
-        // 右 -> 左
-        let mut routes = vec![];
-        for j in (0..storage_j).rev() {
-            routes.push((2 * i, j));
-        }
-        // 降りて左 -> 右
-        for j in 0..=storage_j {
-            routes.push((2 * i + 1, j));
+    // 往復する行の順序を区間ごとに入れ替えてみて, 全体のコストが下がれば採用する.
+    // 入れ替えた区間以降は巻き戻してやり直す
+    let mut checkpoints = vec![];
+    run_sweeps_from(&mut board, &sweeps, 0, &mut checkpoints, storage_j);
+    let mut cost_best = board.cost;
+    for k in 0..sweeps.len() {
+        if sweeps[k].rows.0 == sweeps[k].rows.1 {
+            continue;
         }
 
-        for (idx, &(r_nxt, c_nxt)) in routes.iter().enumerate() {
-            if idx == 0 {
-                if h_total_min < 0 {
-                    board.manual_pop(h_total_min.unsigned_abs());
-                }
-            } else {
-                board.flatten();
-            }
-            board.move_to((r_nxt, c_nxt));
-        }
-        if board.load > 0 {
-            board.manual_push(board.load);
-        }
-
-        // 最終行でなければ, 降りる
-        if 2 * i + 2 < N {
-            board.move_to((2 * i + 2, storage_j));
+        board.rollback(checkpoints[k]);
+        sweeps[k].swapped = true;
+        run_sweeps_from(&mut board, &sweeps, k, &mut checkpoints, storage_j);
+        if board.cost < cost_best {
+            cost_best = board.cost;
+        } else {
+            board.rollback(checkpoints[k]);
+            sweeps[k].swapped = false;
+            run_sweeps_from(&mut board, &sweeps, k, &mut checkpoints, storage_j);
         }
     }
 
-    // 右半分
-    for i in (0..N / 2).rev() {
-        // 往復の積載量計算
-        let mut h_total = 0;
-        let mut h_total_min = 0;
-        for j in 1..storage_j {
-            h_total += board.hnn[2 * i + 1][storage_j + j];
-            h_total_min = h_total_min.min(h_total);
-        }
-        for j in (1..storage_j).rev() {
-            h_total += board.hnn[2 * i][storage_j + j];
-            h_total_min = h_total_min.min(h_total);
-        }
-
-        let mut routes = vec![];
-        // 左 -> 右
-        for j in 1..storage_j {
-            routes.push((2 * i + 1, storage_j + j));
-        }
-        // 上がって右 -> 左
-        for j in (0..storage_j).rev() {
-            routes.push((2 * i, storage_j + j));
-        }
-
-        for (idx, &(r_nxt, c_nxt)) in routes.iter().enumerate() {
-            if idx == 0 {
-                if h_total_min < 0 {
-                    board.manual_pop(h_total_min.unsigned_abs());
-                }
-            } else {
-                board.flatten();
-            }
-            board.move_to((r_nxt, c_nxt));
-        }
-        if board.load > 0 {
-            board.manual_push(board.load);
-        }
+    board
+}
 
-        // 最終行 (行 0) でなければ, 上がる
-        if i != 0 {
-            board.move_to((2 * i - 1, storage_j));
-        }
+/// `sweeps[from..]` を順に実行し, 最後に分割位置の列で辻褄をあわせる.
+/// `checkpoints[k]` には `sweeps[k]` の実行直前の巻き戻し地点を記録する.
+fn run_sweeps_from(
+    board: &mut Board,
+    sweeps: &[RowSweep],
+    from: usize,
+    checkpoints: &mut Vec<Checkpoint>,
+    storage_j: usize,
+) {
+    let n = board.n;
+    checkpoints.truncate(from);
+    for sweep in &sweeps[from..] {
+        checkpoints.push(board.checkpoint());
+        sweep.run(board, storage_j);
     }
 
     // 上 -> 下
     let mut routes = vec![];
-    for i in 1..N {
+    for i in 1..n {
         routes.push((i, storage_j));
     }
     for &(r_nxt, c_nxt) in routes.iter() {
@@ -275,20 +391,255 @@ fn main() {
 
     // 下 -> 上
     let mut routes = vec![];
-    for i in (0..N - 1).rev() {
+    for i in (0..n.saturating_sub(1)).rev() {
         routes.push((i, storage_j));
     }
     for &(r_nxt, c_nxt) in routes.iter() {
         board.flatten();
-        if board.cleared == N * N {
+        if board.cleared == n * n {
             break;
         }
 
         board.move_to((r_nxt, c_nxt));
     }
-    if board.cleared != N * N {
+    if board.cleared != n * n {
         board.flatten();
     }
+}
+
+/// 2 マス間のマンハッタン距離
+fn manhattan((r0, c0): (usize, usize), (r1, c1): (usize, usize)) -> usize {
+    r0.abs_diff(r1) + c0.abs_diff(c1)
+}
+
+/// 非 0 マスの訪問順の初期解を作る.
+/// 現在位置から最も近い, 訪問可能なマスを貪欲に選ぶ.
+/// 凹マスは積荷で埋めきれる場合のみ訪問可能とするため, 各マスは一度の訪問で平らになる.
+fn initial_route(hnn: &[Vec<isize>]) -> Vec<(usize, usize)> {
+    let mut rest = vec![];
+    for (i, row) in hnn.iter().enumerate() {
+        for (j, &v) in row.iter().enumerate() {
+            if v != 0 {
+                rest.push((i, j));
+            }
+        }
+    }
+
+    let mut route = vec![];
+    let mut pos = (0, 0);
+    let mut load = 0;
+    while !rest.is_empty() {
+        // 凸マスが残っている限り積荷は凹マスの総量以下なので, 候補は必ず存在する
+        let idx = (0..rest.len())
+            .filter(|&k| {
+                let (i, j) = rest[k];
+                hnn[i][j] > 0 || hnn[i][j].unsigned_abs() <= load
+            })
+            .min_by_key(|&k| manhattan(pos, rest[k]))
+            .unwrap();
+        let (i, j) = rest.swap_remove(idx);
+        if hnn[i][j] > 0 {
+            load += hnn[i][j].unsigned_abs();
+        } else {
+            load -= hnn[i][j].unsigned_abs();
+        }
+        route.push((i, j));
+        pos = (i, j);
+    }
+
+    route
+}
+
+/// 訪問順 `route` に従ったときのコストと操作回数を返す.
+/// 各マスでは凸なら全量を積み込み, 凹なら全量を下ろす.
+/// 積荷が足りず凹マスを埋めきれない場合や, 操作回数が `TURN_MAX` を超える場合は `None`.
+fn evaluate_route(hnn: &[Vec<isize>], route: &[(usize, usize)]) -> Option<(usize, usize)> {
+    let mut pos = (0, 0);
+    let mut load = 0;
+    let mut cost = 0;
+    let mut turn = 0;
+    for &(i, j) in route {
+        let dist = manhattan(pos, (i, j));
+        cost += dist * (100 + load);
+        turn += dist + 1;
+        let amount = hnn[i][j].unsigned_abs();
+        if hnn[i][j] > 0 {
+            load += amount;
+        } else {
+            if amount > load {
+                return None;
+            }
+            load -= amount;
+        }
+        cost += amount;
+        pos = (i, j);
+    }
+
+    if turn > TURN_MAX {
+        None
+    } else {
+        Some((cost, turn))
+    }
+}
+
+/// 訪問順に対する近傍
+#[derive(Debug, Clone, Copy)]
+enum RouteMove {
+    /// 区間 [l, r) を反転する (2-opt)
+    Reverse(usize, usize),
+    /// 区間 [l, r) を左に `len` 回転する (or-opt, 長さ `len` の区間を後ろへ移動)
+    RotateLeft(usize, usize, usize),
+    /// 区間 [l, r) を右に `len` 回転する (or-opt, 長さ `len` の区間を前へ移動)
+    RotateRight(usize, usize, usize),
+}
+
+impl RouteMove {
+    fn apply(self, route: &mut [(usize, usize)]) {
+        match self {
+            RouteMove::Reverse(l, r) => route[l..r].reverse(),
+            RouteMove::RotateLeft(l, r, len) => route[l..r].rotate_left(len),
+            RouteMove::RotateRight(l, r, len) => route[l..r].rotate_right(len),
+        }
+    }
+
+    fn undo(self, route: &mut [(usize, usize)]) {
+        match self {
+            RouteMove::Reverse(l, r) => route[l..r].reverse(),
+            RouteMove::RotateLeft(l, r, len) => route[l..r].rotate_right(len),
+            RouteMove::RotateRight(l, r, len) => route[l..r].rotate_left(len),
+        }
+    }
+}
+
+/// 訪問順を焼きなましで改善する.
+/// 近傍は 2-opt (区間反転) と or-opt (長さ 3 以下の区間の移動).
+/// 積み下ろし量は訪問順から決まるため, 評価のたびに `evaluate_route` で計算し直す.
+fn optimize_route(
+    hnn: &[Vec<isize>],
+    mut route: Vec<(usize, usize)>,
+    rng: &mut SmallRng,
+    start_time: Instant,
+    break_time: Duration,
+) -> Vec<(usize, usize)> {
+    const OR_OPT_LEN_MAX: usize = 3;
+    const TEMPERATURE_BEGIN: f64 = 50.0;
+    const TEMPERATURE_END: f64 = 1.0;
+
+    let len = route.len();
+    if len < 3 {
+        return route;
+    }
+
+    let Some((mut cost_cur, _)) = evaluate_route(hnn, &route) else {
+        return route;
+    };
+    let mut best_route = route.clone();
+    let mut cost_best = cost_cur;
+    let mut temperature = TEMPERATURE_BEGIN;
+
+    let mut iter = 0usize;
+    loop {
+        if iter % 128 == 0 {
+            let elapsed = start_time.elapsed();
+            if elapsed >= break_time {
+                break;
+            }
+            let progress = elapsed.as_secs_f64() / break_time.as_secs_f64();
+            temperature = TEMPERATURE_BEGIN * (TEMPERATURE_END / TEMPERATURE_BEGIN).powf(progress);
+        }
+        iter += 1;
+
+        let mv = if rng.random::<bool>() {
+            let i = rng.random_range(0..len);
+            let j = rng.random_range(0..len);
+            if i == j {
+                continue;
+            }
+            RouteMove::Reverse(i.min(j), i.max(j) + 1)
+        } else {
+            // 区間 [i, i + seg_len) を位置 k の直前に移動する
+            let seg_len = rng.random_range(1..=OR_OPT_LEN_MAX.min(len - 1));
+            let i = rng.random_range(0..=len - seg_len);
+            let k = rng.random_range(0..=len);
+            if i <= k && k <= i + seg_len {
+                continue;
+            }
+            if k > i + seg_len {
+                RouteMove::RotateLeft(i, k, seg_len)
+            } else {
+                RouteMove::RotateRight(k, i + seg_len, seg_len)
+            }
+        };
+        mv.apply(&mut route);
+
+        let accepted = match evaluate_route(hnn, &route) {
+            Some((cost_nxt, _)) => {
+                let diff = cost_nxt as f64 - cost_cur as f64;
+                if diff <= 0.0 || rng.random::<f64>() < (-diff / temperature).exp() {
+                    cost_cur = cost_nxt;
+                    true
+                } else {
+                    false
+                }
+            }
+            None => false,
+        };
+        if !accepted {
+            mv.undo(&mut route);
+        } else if cost_cur < cost_best {
+            cost_best = cost_cur;
+            best_route.clone_from(&route);
+        }
+    }
+
+    best_route
+}
+
+/// 訪問順 `route` に従ってダンプカーを動かす.
+/// マス間は縦, 横の順に 1 マスずつ移動し, 各マスを平らにする.
+fn solve_with_route(hnn: &[Vec<isize>], route: &[(usize, usize)]) -> Board {
+    let mut board = Board::new(hnn.to_vec());
+    for &(r_nxt, c_nxt) in route {
+        while board.pos.0 != r_nxt {
+            let r = board.pos.0;
+            board.move_to((if r < r_nxt { r + 1 } else { r - 1 }, board.pos.1));
+        }
+        while board.pos.1 != c_nxt {
+            let c = board.pos.1;
+            board.move_to((board.pos.0, if c < c_nxt { c + 1 } else { c - 1 }));
+        }
+        board.flatten();
+    }
+
+    board
+}
+
+#[fastout]
+fn main() {
+    let start_time = Instant::now();
+    let break_time = Duration::from_millis(RUN_TIME_LIMIT_MS);
+
+    input! {
+        n: usize,
+        hnn: [[isize; n]; n],
+    }
+
+    let mut rng = SmallRng::from_os_rng();
+
+    let rule_based = solve_rule_based(&hnn);
+
+    // 非 0 マスの訪問順を最適化する
+    let route = optimize_route(&hnn, initial_route(&hnn), &mut rng, start_time, break_time);
+    let optimized = solve_with_route(&hnn, &route);
+
+    let board = if optimized.cleared == n * n
+        && optimized.operations.len() <= TURN_MAX
+        && optimized.cost < rule_based.cost
+    {
+        optimized
+    } else {
+        rule_based
+    };
 
     for a in board.operations {
         println!("{a}");
@@ -0,0 +1,184 @@
+// 操作列を再生してコストと残りの高さを出力する
+//
+// cargo run -r --bin checker <input> <output> [--allow-borrow]
+//
+// 既定では現在の高さを超える積み込み (前借り) も不正とする.
+// `--allow-borrow` を付けると高さが負になる積み込みを許す.
+// `a.rs` の行ごとの掃き出しは `storage_j` の列で前借りするため, その出力の検証には `--allow-borrow` が必要.
+
+use std::process::exit;
+
+const TURN_MAX: usize = 100_000;
+
+#[derive(Debug, Clone, Copy)]
+enum Operation {
+    /// ダンプカーに積み込む, 現在位置に対しての pop
+    Pop(usize),
+    /// ダンプカーから下ろす, 現在位置に対しての push
+    Push(usize),
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::Pop(x) => write!(f, "+{x}"),
+            Operation::Push(x) => write!(f, "-{x}"),
+            Operation::Up => write!(f, "U"),
+            Operation::Down => write!(f, "D"),
+            Operation::Left => write!(f, "L"),
+            Operation::Right => write!(f, "R"),
+        }
+    }
+}
+
+impl std::str::FromStr for Operation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_amount = |x: &str| {
+            x.parse::<usize>()
+                .ok()
+                .filter(|&d| d > 0)
+                .ok_or_else(|| format!("invalid amount: {s}"))
+        };
+        match s {
+            "U" => Ok(Operation::Up),
+            "D" => Ok(Operation::Down),
+            "L" => Ok(Operation::Left),
+            "R" => Ok(Operation::Right),
+            _ => {
+                if let Some(x) = s.strip_prefix('+') {
+                    Ok(Operation::Pop(parse_amount(x)?))
+                } else if let Some(x) = s.strip_prefix('-') {
+                    Ok(Operation::Push(parse_amount(x)?))
+                } else {
+                    Err(format!("unknown operation: {s}"))
+                }
+            }
+        }
+    }
+}
+
+/// `a.rs` の `Board` から, 不正な操作を assert ではなく `Err` で返すようにしたもの
+struct Board {
+    n: usize,
+    load: usize,
+    hnn: Vec<Vec<isize>>,
+    pos: (usize, usize),
+    cost: usize,
+    allow_borrow: bool,
+}
+
+impl Board {
+    fn new(hnn: Vec<Vec<isize>>, allow_borrow: bool) -> Self {
+        Self {
+            n: hnn.len(),
+            load: 0,
+            hnn,
+            pos: (0, 0),
+            cost: 0,
+            allow_borrow,
+        }
+    }
+
+    fn work(&mut self, op: Operation) -> Result<(), String> {
+        let (i, j) = self.pos;
+        match op {
+            Operation::Pop(x) => {
+                if !self.allow_borrow && self.hnn[i][j] < x as isize {
+                    return Err(format!(
+                        "cannot pop {x} from ({i}, {j}) whose height is {} (use --allow-borrow to permit borrowing)",
+                        self.hnn[i][j]
+                    ));
+                }
+                self.hnn[i][j] -= x as isize;
+                self.load += x;
+                self.cost += x;
+            }
+            Operation::Push(x) => {
+                if x > self.load {
+                    return Err(format!("cannot push {x} with load {}", self.load));
+                }
+                self.hnn[i][j] += x as isize;
+                self.load -= x;
+                self.cost += x;
+            }
+            Operation::Up | Operation::Down | Operation::Left | Operation::Right => {
+                let (di, dj) = match op {
+                    Operation::Up => (-1, 0),
+                    Operation::Down => (1, 0),
+                    Operation::Left => (0, -1),
+                    _ => (0, 1),
+                };
+                let i_nxt = i.wrapping_add_signed(di);
+                let j_nxt = j.wrapping_add_signed(dj);
+                if i_nxt >= self.n || j_nxt >= self.n {
+                    return Err(format!("cannot move {op} from ({i}, {j})"));
+                }
+                self.pos = (i_nxt, j_nxt);
+                self.cost += 100 + self.load;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let allow_borrow = args.iter().any(|a| a == "--allow-borrow");
+    let files: Vec<&String> = args.iter().filter(|a| !a.starts_with("--")).collect();
+    if files.len() != 2 {
+        eprintln!("usage: checker <input> <output> [--allow-borrow]");
+        eprintln!(
+            "  --allow-borrow  allow popping more than the current height (needed for a.rs output)"
+        );
+        exit(2);
+    }
+
+    let input = std::fs::read_to_string(files[0]).expect("failed to read input");
+    let mut tokens = input
+        .split_whitespace()
+        .map(|tok| tok.parse::<isize>().expect("failed to parse isize"));
+    let n = tokens.next().expect("missing N") as usize;
+    let hnn: Vec<Vec<isize>> = (0..n)
+        .map(|_| (0..n).map(|_| tokens.next().expect("missing h")).collect())
+        .collect();
+    let base: usize = hnn.iter().flatten().map(|v| v.unsigned_abs()).sum();
+
+    let output = std::fs::read_to_string(files[1]).expect("failed to read output");
+    let mut board = Board::new(hnn, allow_borrow);
+    for (turn, tok) in output.split_whitespace().enumerate() {
+        if turn >= TURN_MAX {
+            eprintln!("too many operations: more than {TURN_MAX}");
+            exit(1);
+        }
+        if let Err(e) = tok.parse::<Operation>().and_then(|op| board.work(op)) {
+            eprintln!("operation {turn}: {e}");
+            exit(1);
+        }
+    }
+
+    let cleared = board.hnn.iter().flatten().all(|&v| v == 0);
+    let score = if cleared {
+        (1e9 * base as f64 / board.cost as f64).round() as usize
+    } else {
+        0
+    };
+    println!("Cost = {}", board.cost);
+    println!("Load = {}", board.load);
+    println!("Score = {score}");
+    for row in &board.hnn {
+        println!(
+            "{}",
+            row.iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+    }
+}
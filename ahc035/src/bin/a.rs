@@ -1,10 +1,27 @@
 use proconio::input;
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
 use std::collections::VecDeque;
 use std::io::{self, Write};
 use std::time::Instant;
 
 const HC_TURNS: usize = 2;
 const HC_LIMIT_MS: u128 = 1800;
+/// Run the hill climbers on several threads; `false` runs climber 0 alone on
+/// the calling thread.
+const HC_PARALLEL: bool = true;
+/// Upper bound on the parallel climbers, further capped by the available CPUs.
+const HC_THREADS: usize = 4;
+/// Climber `id > 0` is seeded with `HC_SEED + id`.
+const HC_SEED: u64 = 0;
+const LOOKAHEAD_LIMIT_MS: u128 = 1000;
+const ROLLOUTS: usize = 8;
+const COVERAGE_WEIGHT: f64 = 1.0;
+const POLICY: LayoutPolicy = LayoutPolicy::Lookahead;
+const SIMULATION_RUNS: usize = 10;
+/// Print `report_turn` to stderr after every turn.
+const DEBUG_REPORT: bool = cfg!(debug_assertions);
 type Cell = (usize, usize);
 
 /// Return the cells in the order specified by the document: BFS from (3, 3),
@@ -186,118 +203,223 @@ fn child_cdf(a: &[usize], b: &[usize], max_value: usize) -> Vec<f64> {
     probability
 }
 
-fn edge_cdfs(
-    layout: &[Vec<usize>],
-    seeds: &[Vec<usize>],
-    edges: &[(Cell, Cell)],
+/// Distribution of the best child value under a layout, kept up to date
+/// across swaps.  For every value `v` it caches how many edges have
+/// `Pr(V_k <= v) = 0` and the sum of `ln Pr(V_k <= v)` over the other edges,
+/// so swapping two cells only revisits the edges around them.  Child CDFs
+/// are cached per seed pair, as a swap only rearranges the same seeds.
+struct ExpectedMaximum {
+    seed_count: usize,
     max_value: usize,
-) -> Vec<Vec<f64>> {
-    edges
-        .iter()
-        .map(|&((r1, c1), (r2, c2))| {
-            child_cdf(&seeds[layout[r1][c1]], &seeds[layout[r2][c2]], max_value)
-        })
-        .collect()
+    edges: Vec<(Cell, Cell)>,
+    cell_edges: Vec<Vec<Vec<usize>>>,
+    /// `ln Pr(V <= v)` of the child of seeds `a` and `b` at `a * seed_count + b`,
+    /// or empty if not computed yet
+    pair_log_cdfs: Vec<Vec<f64>>,
+    zero_count: Vec<usize>,
+    log_sum: Vec<f64>,
 }
 
-fn cdf_product_metadata(cdfs: &[Vec<f64>], max_value: usize) -> (Vec<usize>, Vec<f64>) {
-    let mut zero_count = vec![0; max_value + 1];
-    let mut nonzero_product = vec![1.0; max_value + 1];
-    for cdf in cdfs {
-        for value in 0..=max_value {
-            if cdf[value] == 0.0 {
-                zero_count[value] += 1;
-            } else {
-                nonzero_product[value] *= cdf[value];
-            }
+impl ExpectedMaximum {
+    fn new(layout: &[Vec<usize>], seeds: &[Vec<usize>], n: usize, max_value: usize) -> Self {
+        let edges = grid_edges(n);
+        let mut cell_edges = vec![vec![vec![]; n]; n];
+        for (index, &((r1, c1), (r2, c2))) in edges.iter().enumerate() {
+            cell_edges[r1][c1].push(index);
+            cell_edges[r2][c2].push(index);
         }
+
+        let mut engine = Self {
+            seed_count: seeds.len(),
+            max_value,
+            edges,
+            cell_edges,
+            pair_log_cdfs: vec![vec![]; seeds.len() * seeds.len()],
+            zero_count: vec![0; max_value + 1],
+            log_sum: vec![0.0; max_value + 1],
+        };
+        for index in 0..engine.edges.len() {
+            let key = engine.edge_key(layout, seeds, index, None);
+            engine.add(key, 1);
+        }
+        engine
+    }
+
+    /// Key of the seed pair planted on edge `index`, after swapping the two
+    /// cells of `swap` if given.
+    fn edge_key(
+        &mut self,
+        layout: &[Vec<usize>],
+        seeds: &[Vec<usize>],
+        index: usize,
+        swap: Option<(Cell, Cell)>,
+    ) -> usize {
+        let seed_at = |cell: Cell| match swap {
+            Some((first, second)) if cell == first => layout[second.0][second.1],
+            Some((first, second)) if cell == second => layout[first.0][first.1],
+            _ => layout[cell.0][cell.1],
+        };
+        let (a, b) = self.edges[index];
+        let (a, b) = (seed_at(a), seed_at(b));
+        let key = a.min(b) * self.seed_count + a.max(b);
+        if self.pair_log_cdfs[key].is_empty() {
+            self.pair_log_cdfs[key] = child_cdf(&seeds[a], &seeds[b], self.max_value)
+                .into_iter()
+                .map(f64::ln)
+                .collect();
+        }
+        key
     }
-    (zero_count, nonzero_product)
-}
 
-fn expected_maximum(zero_count: &[usize], nonzero_product: &[f64], max_value: usize) -> f64 {
-    (0..max_value)
-        .map(|value| {
-            if zero_count[value] == 0 {
-                1.0 - nonzero_product[value]
+    /// Add (`sign = 1`) or remove (`sign = -1`) the child CDF of pair `key`.
+    fn add(&mut self, key: usize, sign: isize) {
+        for value in 0..self.max_value {
+            let log_cdf = self.pair_log_cdfs[key][value];
+            if log_cdf == f64::NEG_INFINITY {
+                self.zero_count[value] = self.zero_count[value].wrapping_add_signed(sign);
             } else {
-                1.0
+                self.log_sum[value] += sign as f64 * log_cdf;
             }
-        })
-        .sum()
-}
-
-fn seed_after_swap(layout: &[Vec<usize>], cell: Cell, first: Cell, second: Cell) -> usize {
-    if cell == first {
-        layout[second.0][second.1]
-    } else if cell == second {
-        layout[first.0][first.1]
-    } else {
-        layout[cell.0][cell.1]
+        }
     }
-}
 
-fn affected_edges(edges: &[(Cell, Cell)], first: Cell, second: Cell) -> Vec<usize> {
-    edges
-        .iter()
-        .enumerate()
-        .filter_map(|(index, &(a, b))| {
-            (a == first || b == first || a == second || b == second).then_some(index)
-        })
-        .collect()
-}
+    /// `F(A) = E[max_k V_k]` of the current layout.
+    fn value(&self) -> f64 {
+        (0..self.max_value)
+            .map(|value| {
+                if self.zero_count[value] == 0 {
+                    1.0 - self.log_sum[value].exp()
+                } else {
+                    1.0
+                }
+            })
+            .sum()
+    }
 
-fn swapped_expected_maximum(
-    layout: &[Vec<usize>],
-    seeds: &[Vec<usize>],
-    edges: &[(Cell, Cell)],
-    cdfs: &[Vec<f64>],
-    zero_count: &[usize],
-    nonzero_product: &[f64],
-    max_value: usize,
-    first: Cell,
-    second: Cell,
-) -> f64 {
-    let affected = affected_edges(edges, first, second);
-    let new_cdfs: Vec<Vec<f64>> = affected
-        .iter()
-        .map(|&edge_index| {
-            let (a, b) = edges[edge_index];
-            let first_seed = seed_after_swap(layout, a, first, second);
-            let second_seed = seed_after_swap(layout, b, first, second);
-            child_cdf(&seeds[first_seed], &seeds[second_seed], max_value)
-        })
-        .collect();
+    /// Keys of the pairs that swapping `first` and `second` removes and adds.
+    /// The edge between the two cells keeps the same pair and is skipped.
+    fn swap_keys(
+        &mut self,
+        layout: &[Vec<usize>],
+        seeds: &[Vec<usize>],
+        first: Cell,
+        second: Cell,
+    ) -> Vec<(usize, usize)> {
+        let mut affected = self.cell_edges[first.0][first.1].clone();
+        affected.extend(&self.cell_edges[second.0][second.1]);
+        affected.sort_unstable();
+        affected.dedup();
+        affected.retain(|&index| {
+            let (a, b) = self.edges[index];
+            !((a == first && b == second) || (a == second && b == first))
+        });
+        affected
+            .into_iter()
+            .map(|index| {
+                (
+                    self.edge_key(layout, seeds, index, None),
+                    self.edge_key(layout, seeds, index, Some((first, second))),
+                )
+            })
+            .collect()
+    }
 
-    let mut expectation = 0.0;
-    for value in 0..max_value {
-        let mut zeros = zero_count[value];
-        let mut product = nonzero_product[value];
-        for (position, &edge_index) in affected.iter().enumerate() {
-            let old = cdfs[edge_index][value];
-            if old == 0.0 {
-                zeros -= 1;
-            } else {
-                product /= old;
+    /// `F(A)` after swapping `first` and `second`, without applying the swap.
+    fn swapped_value(
+        &mut self,
+        layout: &[Vec<usize>],
+        seeds: &[Vec<usize>],
+        first: Cell,
+        second: Cell,
+    ) -> f64 {
+        let keys = self.swap_keys(layout, seeds, first, second);
+        let mut expectation = 0.0;
+        for value in 0..self.max_value {
+            let mut zeros = self.zero_count[value];
+            let mut log_sum = self.log_sum[value];
+            for &(old, new) in &keys {
+                let old = self.pair_log_cdfs[old][value];
+                if old == f64::NEG_INFINITY {
+                    zeros -= 1;
+                } else {
+                    log_sum -= old;
+                }
+                let new = self.pair_log_cdfs[new][value];
+                if new == f64::NEG_INFINITY {
+                    zeros += 1;
+                } else {
+                    log_sum += new;
+                }
             }
+            expectation += if zeros == 0 { 1.0 - log_sum.exp() } else { 1.0 };
+        }
+        expectation
+    }
 
-            let new = new_cdfs[position][value];
-            if new == 0.0 {
-                zeros += 1;
-            } else {
-                product *= new;
-            }
+    /// Swap `first` and `second` in `layout` and update the cached sums.
+    fn swap(&mut self, layout: &mut [Vec<usize>], seeds: &[Vec<usize>], first: Cell, second: Cell) {
+        for (old, new) in self.swap_keys(layout, seeds, first, second) {
+            self.add(old, -1);
+            self.add(new, 1);
         }
-        expectation += if zeros == 0 { 1.0 - product } else { 1.0 };
+        let temporary = layout[first.0][first.1];
+        layout[first.0][first.1] = layout[second.0][second.1];
+        layout[second.0][second.1] = temporary;
     }
-    expectation
 }
 
+/// Run up to `HC_THREADS` climbers in parallel and keep the layout with the
+/// highest expected maximum.  Climber 0 is the deterministic best-improvement
+/// climb; the others are seeded from `HC_SEED` and their index and take the
+/// first improving swap in a shuffled order, so they end in different local
+/// optima.  With `HC_PARALLEL` off, or on a single CPU, only climber 0 runs.
 fn hill_climb(layout: &mut [Vec<usize>], seeds: &[Vec<usize>], n: usize, start: &Instant) {
-    let edges = grid_edges(n);
-    let max_value: usize = (0..seeds[0].len())
-        .map(|component| seeds.iter().map(|seed| seed[component]).max().unwrap())
-        .sum();
+    let threads = if HC_PARALLEL {
+        let cpus = std::thread::available_parallelism().map_or(1, |cpus| cpus.get());
+        HC_THREADS.min(cpus)
+    } else {
+        1
+    };
+    if threads <= 1 {
+        climb(layout, seeds, n, start, None);
+        return;
+    }
+
+    let base: &[Vec<usize>] = layout;
+    let (_, best) = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|id| {
+                scope.spawn(move || {
+                    let mut candidate = base.to_vec();
+                    let mut rng = (id > 0).then(|| SmallRng::seed_from_u64(HC_SEED + id as u64));
+                    let score = climb(&mut candidate, seeds, n, start, rng.as_mut());
+                    (score, candidate)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .max_by(|left, right| left.0.total_cmp(&right.0))
+            .unwrap()
+    });
+    for (row, best_row) in layout.iter_mut().zip(best) {
+        *row = best_row;
+    }
+}
+
+/// Swap two inner or two outer cells while `F(A)` improves and return the
+/// final `F(A)`.  Without `rng` the best swap of each round is taken; with
+/// it the swaps are tried in a shuffled order and the first improving one
+/// is taken.
+fn climb(
+    layout: &mut [Vec<usize>],
+    seeds: &[Vec<usize>],
+    n: usize,
+    start: &Instant,
+    mut rng: Option<&mut SmallRng>,
+) -> f64 {
+    let max_value = coverage(seeds);
     let inner: Vec<Cell> = (0..n)
         .flat_map(|r| (0..n).map(move |c| (r, c)))
         .filter(|&(r, c)| r > 0 && r + 1 < n && c > 0 && c + 1 < n)
@@ -306,49 +428,44 @@ fn hill_climb(layout: &mut [Vec<usize>], seeds: &[Vec<usize>], n: usize, start:
         .flat_map(|r| (0..n).map(move |c| (r, c)))
         .filter(|cell| !inner.contains(cell))
         .collect();
+    let mut pairs = vec![];
+    for cells in [&inner, &outer] {
+        for i in 0..cells.len() {
+            for j in i + 1..cells.len() {
+                pairs.push((cells[i], cells[j]));
+            }
+        }
+    }
 
-    let mut cdfs = edge_cdfs(layout, seeds, &edges, max_value);
-    let (mut zero_count, mut nonzero_product) = cdf_product_metadata(&cdfs, max_value);
-    let mut current = expected_maximum(&zero_count, &nonzero_product, max_value);
+    let mut engine = ExpectedMaximum::new(layout, seeds, n, max_value);
+    let mut current = engine.value();
 
     loop {
+        if let Some(rng) = rng.as_mut() {
+            pairs.shuffle(rng);
+        }
         let mut best = None;
-        for cells in [&inner, &outer] {
-            for i in 0..cells.len() {
-                for j in i + 1..cells.len() {
-                    if start.elapsed().as_millis() >= HC_LIMIT_MS {
-                        return;
-                    }
-                    let score = swapped_expected_maximum(
-                        layout,
-                        seeds,
-                        &edges,
-                        &cdfs,
-                        &zero_count,
-                        &nonzero_product,
-                        max_value,
-                        cells[i],
-                        cells[j],
-                    );
-                    if score > current + 1e-9
-                        && best
-                            .as_ref()
-                            .is_none_or(|&(best_score, _, _)| score > best_score)
-                    {
-                        best = Some((score, cells[i], cells[j]));
-                    }
+        for &(first, second) in &pairs {
+            if start.elapsed().as_millis() >= HC_LIMIT_MS {
+                return current;
+            }
+            let score = engine.swapped_value(layout, seeds, first, second);
+            if score > current + 1e-9
+                && best
+                    .as_ref()
+                    .is_none_or(|&(best_score, _, _)| score > best_score)
+            {
+                best = Some((score, first, second));
+                if rng.is_some() {
+                    break;
                 }
             }
         }
 
         let Some((score, first, second)) = best else {
-            return;
+            return current;
         };
-        let temporary = layout[first.0][first.1];
-        layout[first.0][first.1] = layout[second.0][second.1];
-        layout[second.0][second.1] = temporary;
-        cdfs = edge_cdfs(layout, seeds, &edges, max_value);
-        (zero_count, nonzero_product) = cdf_product_metadata(&cdfs, max_value);
+        engine.swap(layout, seeds, first, second);
         current = score;
     }
 }
@@ -420,6 +537,301 @@ fn make_layout(
     layout
 }
 
+/// Layout strategies that `simulate` can compare offline.
+#[derive(Clone, Copy, Debug)]
+enum LayoutPolicy {
+    /// The greedy complementary layout on every turn.
+    Greedy,
+    /// The greedy layout, refined by `hill_climb` in the last `HC_TURNS` turns.
+    HillClimb,
+    /// `plan_layout` before the last `HC_TURNS` turns, then `HillClimb`.
+    Lookahead,
+}
+
+impl LayoutPolicy {
+    const ALL: [LayoutPolicy; 3] = [
+        LayoutPolicy::Greedy,
+        LayoutPolicy::HillClimb,
+        LayoutPolicy::Lookahead,
+    ];
+
+    fn layout(
+        self,
+        seeds: &[Vec<usize>],
+        n: usize,
+        turn: usize,
+        t: usize,
+        start: &Instant,
+        rng: &mut SmallRng,
+    ) -> Vec<Vec<usize>> {
+        match self {
+            LayoutPolicy::Greedy => make_layout(seeds, n, false, start),
+            LayoutPolicy::HillClimb => make_layout(seeds, n, turn + HC_TURNS >= t, start),
+            LayoutPolicy::Lookahead => {
+                if turn + HC_TURNS >= t {
+                    make_layout(seeds, n, true, start)
+                } else {
+                    plan_layout(seeds, n, turn, t, start, rng)
+                }
+            }
+        }
+    }
+}
+
+/// Sum of the per-criterion maxima over `seeds`.  No later generation can
+/// have a seed worth more than this, so it measures how much of every
+/// criterion's maximum is still available for breeding.
+fn coverage(seeds: &[Vec<usize>]) -> usize {
+    (0..seeds[0].len())
+        .map(|component| seeds.iter().map(|seed| seed[component]).max().unwrap())
+        .sum()
+}
+
+/// Average outcome of planting `layout` now and then playing the greedy
+/// layout until the end, over one rollout per entry of `rollout_seeds`.
+/// The outcome is the final best seed value plus `COVERAGE_WEIGHT` times the
+/// coverage of the generation bred from `layout`.
+fn rollout_value(
+    layout: &[Vec<usize>],
+    seeds: &[Vec<usize>],
+    n: usize,
+    turn: usize,
+    t: usize,
+    start: &Instant,
+    rollout_seeds: &[u64],
+) -> f64 {
+    let edges = grid_edges(n);
+    let mut total = 0.0;
+    for &rollout_seed in rollout_seeds {
+        let mut rng = SmallRng::seed_from_u64(rollout_seed);
+        let mut generation = breed(layout, seeds, &edges, &mut rng);
+        let bred_coverage = coverage(&generation);
+        for _ in turn + 1..t {
+            let next_layout = make_layout(&generation, n, false, start);
+            generation = breed(&next_layout, &generation, &edges, &mut rng);
+        }
+        let best = generation
+            .iter()
+            .map(|seed| total_value(seed))
+            .max()
+            .unwrap();
+        total += best as f64 + COVERAGE_WEIGHT * bred_coverage as f64;
+    }
+    total / rollout_seeds.len() as f64
+}
+
+/// Improve the greedy layout for a non-final turn by swapping two inner or
+/// two outer cells, keeping a swap only when simulated rollouts of the
+/// remaining generations get better.  Every candidate is rolled out with the
+/// same random seeds so that they are compared on the same inheritance luck.
+/// The turns before the last `HC_TURNS` share `LOOKAHEAD_LIMIT_MS` evenly.
+fn plan_layout(
+    seeds: &[Vec<usize>],
+    n: usize,
+    turn: usize,
+    t: usize,
+    start: &Instant,
+    rng: &mut SmallRng,
+) -> Vec<Vec<usize>> {
+    let mut best = make_layout(seeds, n, false, start);
+    let planned_turns = t.saturating_sub(HC_TURNS).max(1);
+    let deadline = LOOKAHEAD_LIMIT_MS * (turn + 1) as u128 / planned_turns as u128;
+
+    let is_inner = |(r, c): Cell| r > 0 && r + 1 < n && c > 0 && c + 1 < n;
+    let cells: Vec<Cell> = (0..n).flat_map(|r| (0..n).map(move |c| (r, c))).collect();
+    let rollout_seeds: Vec<u64> = (0..ROLLOUTS).map(|_| rng.random()).collect();
+    let mut best_value = rollout_value(&best, seeds, n, turn, t, start, &rollout_seeds);
+
+    while start.elapsed().as_millis() < deadline {
+        let first = cells[rng.random_range(0..cells.len())];
+        let second = cells[rng.random_range(0..cells.len())];
+        if first == second || is_inner(first) != is_inner(second) {
+            continue;
+        }
+
+        let mut candidate = best.clone();
+        candidate[first.0][first.1] = best[second.0][second.1];
+        candidate[second.0][second.1] = best[first.0][first.1];
+        let value = rollout_value(&candidate, seeds, n, turn, t, start, &rollout_seeds);
+        if value > best_value {
+            best = candidate;
+            best_value = value;
+        }
+    }
+    best
+}
+
+/// Harvest one child from every edge of the planted layout.  Each criterion
+/// of a child is inherited from either parent with probability 1/2, and the
+/// children are returned in `grid_edges` order.
+fn breed(
+    layout: &[Vec<usize>],
+    seeds: &[Vec<usize>],
+    edges: &[(Cell, Cell)],
+    rng: &mut SmallRng,
+) -> Vec<Vec<usize>> {
+    edges
+        .iter()
+        .map(|&((r1, c1), (r2, c2))| {
+            let a = &seeds[layout[r1][c1]];
+            let b = &seeds[layout[r2][c2]];
+            a.iter()
+                .zip(b)
+                .map(|(&av, &bv)| if rng.random::<bool>() { av } else { bv })
+                .collect()
+        })
+        .collect()
+}
+
+/// Play all `t` turns against a simulated judge, starting from `seeds`, and
+/// return the value of the best seed in the final generation.
+fn simulate(
+    seeds: &[Vec<usize>],
+    n: usize,
+    t: usize,
+    policy: LayoutPolicy,
+    rng: &mut SmallRng,
+) -> usize {
+    let start = Instant::now();
+    let edges = grid_edges(n);
+    let mut seeds = seeds.to_vec();
+    for turn in 0..t {
+        let layout = policy.layout(&seeds, n, turn, t, &start, rng);
+        seeds = breed(&layout, &seeds, &edges, rng);
+    }
+    seeds.iter().map(|seed| total_value(seed)).max().unwrap()
+}
+
+/// Compare every `LayoutPolicy` on the same initial seeds.  The score is
+/// scaled as the judge does, by the sum of the initial per-criterion maxima.
+fn compare_policies(seeds: &[Vec<usize>], n: usize, t: usize, runs: usize) {
+    let denominator = coverage(seeds);
+    for policy in LayoutPolicy::ALL {
+        let values: Vec<usize> = (0..runs)
+            .map(|run| {
+                simulate(
+                    seeds,
+                    n,
+                    t,
+                    policy,
+                    &mut SmallRng::seed_from_u64(run as u64),
+                )
+            })
+            .collect();
+        let average = values.iter().sum::<usize>() as f64 / runs as f64;
+        eprintln!(
+            "{policy:?}: average {average:.1}, min {}, max {}",
+            values.iter().min().unwrap(),
+            values.iter().max().unwrap()
+        );
+        println!(
+            "{policy:?} Score = {}",
+            (1e6 * average / denominator as f64).round() as usize
+        );
+    }
+}
+
+/// Probability that no child of `layout` inherits the maximum of every
+/// criterion.  Each edge with exactly one parent holding the maximum passes
+/// it on with probability 1/2, and an edge with both always does.
+fn max_loss_probabilities(layout: &[Vec<usize>], seeds: &[Vec<usize>], n: usize) -> Vec<f64> {
+    let edges = grid_edges(n);
+    (0..seeds[0].len())
+        .map(|component| {
+            let maximum = seeds.iter().map(|seed| seed[component]).max().unwrap();
+            edges
+                .iter()
+                .map(|&((r1, c1), (r2, c2))| {
+                    let holders = [layout[r1][c1], layout[r2][c2]]
+                        .iter()
+                        .filter(|&&seed| seeds[seed][component] == maximum)
+                        .count();
+                    [1.0, 0.5, 0.0][holders]
+                })
+                .product()
+        })
+        .collect()
+}
+
+/// Debug report of one turn: the holder of each criterion's maximum and
+/// where it is planted, the protected set, the criteria that may lose their
+/// maximum in the next generation with probability 0.1% or more, and the
+/// expected versus realised maxima.
+/// `next_seeds` is `None` on the last turn, whose harvest is not returned.
+fn report_turn(
+    turn: usize,
+    seeds: &[Vec<usize>],
+    layout: &[Vec<usize>],
+    next_seeds: Option<&[Vec<usize>]>,
+    n: usize,
+) {
+    let mut cell_of = vec![None; seeds.len()];
+    for (r, row) in layout.iter().enumerate() {
+        for (c, &seed) in row.iter().enumerate() {
+            cell_of[seed] = Some((r, c));
+        }
+    }
+    let inner_count = n.saturating_sub(2).pow(2);
+    let protected = protected_seeds(seeds, inner_count);
+    let loss = max_loss_probabilities(layout, seeds, n);
+
+    eprintln!("turn {turn}: protected {protected:?}");
+    for (component, &loss_probability) in loss.iter().enumerate() {
+        let holder = best_seed_for_component(seeds, component, None);
+        let place = match cell_of[holder] {
+            Some(cell) => format!("at {cell:?}"),
+            None => "not planted".to_string(),
+        };
+        let realised = next_seeds.map_or("-".to_string(), |next| {
+            next.iter()
+                .map(|seed| seed[component])
+                .max()
+                .unwrap()
+                .to_string()
+        });
+        eprintln!(
+            "turn {turn}: criterion {component:2}: max {:3} by seed {holder:2} {place}{}, P(lost) {loss_probability:.3}, next max {realised}",
+            seeds[holder][component],
+            if protected.contains(&holder) {
+                " [protected]"
+            } else {
+                ""
+            },
+        );
+    }
+
+    let mut at_risk: Vec<(usize, f64)> = loss
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|&(_, probability)| probability >= 1e-3)
+        .collect();
+    at_risk.sort_unstable_by(|left, right| right.1.total_cmp(&left.1));
+    eprintln!(
+        "turn {turn}: at risk {}",
+        at_risk
+            .iter()
+            .map(|(component, probability)| format!("{component} ({probability:.3})"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let expected = ExpectedMaximum::new(layout, seeds, n, coverage(seeds)).value();
+    let realised = next_seeds.map_or("-".to_string(), |next| {
+        next.iter()
+            .map(|seed| total_value(seed))
+            .max()
+            .unwrap()
+            .to_string()
+    });
+    eprintln!(
+        "turn {turn}: best value now {}, expected {expected:.1}, realised {realised}; coverage now {}{}",
+        seeds.iter().map(|seed| total_value(seed)).max().unwrap(),
+        coverage(seeds),
+        next_seeds.map_or(String::new(), |next| format!(", next {}", coverage(next))),
+    );
+}
+
 fn main() {
     let start = Instant::now();
     input! {
@@ -432,8 +844,19 @@ fn main() {
         mut seeds: [[usize; m]; seed_count],
     }
 
+    // `a --simulate [runs] < in/0000.txt` compares the layout policies offline
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "--simulate") {
+        let runs = args.get(1).map_or(SIMULATION_RUNS, |arg| {
+            arg.parse().expect("runs must be a number")
+        });
+        compare_policies(&seeds, n, t, runs);
+        return;
+    }
+
+    let mut rng = SmallRng::from_os_rng();
     for turn in 0..t {
-        let layout = make_layout(&seeds, n, turn + HC_TURNS >= t, &start);
+        let layout = POLICY.layout(&seeds, n, turn, t, &start, &mut rng);
         for row in &layout {
             println!(
                 "{}",
@@ -449,7 +872,12 @@ fn main() {
             input! {
                 next_seeds: [[usize; m]; seed_count],
             }
+            if DEBUG_REPORT {
+                report_turn(turn, &seeds, &layout, Some(&next_seeds), n);
+            }
             seeds = next_seeds;
+        } else if DEBUG_REPORT {
+            report_turn(turn, &seeds, &layout, None, n);
         }
     }
 }
@@ -1,10 +1,9 @@
 use proconio::fastout;
 use proconio::input;
 use rand::rngs::SmallRng;
-use rand::seq::SliceRandom;
 use rand::{Rng, SeedableRng};
-use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::collections::HashMap;
 use std::fmt::{self, Formatter};
 use std::time::{Duration, Instant};
 
@@ -19,13 +18,20 @@ macro_rules! debug {
 }
 
 const DUMMY: usize = usize::MAX / 4;
-#[allow(dead_code)]
 const OPERATION_CNT_LIMIT: usize = 100_000;
 #[allow(dead_code)]
 const RUN_TIME_LIMIT_MS: u64 = if cfg!(debug_assertions) { 100 } else { 2800 };
 
-const B_UPDATE_BEGIN_PTRN_NUM: usize = 5;
-const B_UPDATE_LEN_MAX: usize = 8;
+const B_UPDATE_BEGIN_PTRN_NUM: usize = 24;
+const B_UPDATE_LEN_MAX: usize = 24;
+
+/// true なら A を作り直して最良を取った後, 残り時間で最良の A を焼きなましで改善する
+/// false なら時間いっぱい A を作り直して最良を取る
+const A_ANNEALING: bool = true;
+/// A の作り直しに使う時間の割合
+const A_RESAMPLE_TIME_RATIO: f64 = 0.5;
+const A_ANNEALING_TEMP_BEGIN: f64 = 1.0;
+const A_ANNEALING_TEMP_END: f64 = 0.1;
 
 #[derive(Clone, Debug)]
 enum Operation {
@@ -53,6 +59,370 @@ impl std::fmt::Display for Operation {
     }
 }
 
+/// `v_start` から `v_end` への経路を 0-1 BFS で求める. 経路は始点を含まず終点を含む.
+/// 青信号の都市へは信号操作なしに進めるためコスト 0, 赤信号の都市はコスト 1 とする.
+/// A に含まれない都市は青にできないため通らない.
+/// 信号操作の回数が同じ経路同士では, 移動回数の少ない方を選ぶ.
+fn signal_aware_path(
+    edges: &[Vec<usize>],
+    v_start: usize,
+    v_end: usize,
+    open_cnt: &[usize],
+    in_a: &[bool],
+) -> Option<Vec<usize>> {
+    let n = edges.len();
+    // (赤信号の都市数, 移動回数)
+    let mut dist = vec![(DUMMY, DUMMY); n];
+    let mut comes_from = vec![None; n];
+    let mut q = VecDeque::new();
+    dist[v_start] = (0, 0);
+    q.push_back(v_start);
+    while let Some(v_cur) = q.pop_front() {
+        let (closed_cur, moves_cur) = dist[v_cur];
+        for &v_nxt in &edges[v_cur] {
+            if !in_a[v_nxt] {
+                continue;
+            }
+
+            let is_closed = open_cnt[v_nxt] == 0;
+            let d_nxt = (closed_cur + is_closed as usize, moves_cur + 1);
+            if d_nxt >= dist[v_nxt] {
+                continue;
+            }
+
+            dist[v_nxt] = d_nxt;
+            comes_from[v_nxt] = Some(v_cur);
+            if is_closed {
+                q.push_back(v_nxt);
+            } else {
+                q.push_front(v_nxt);
+            }
+        }
+    }
+
+    comes_from[v_end]?;
+    let mut path = vec![v_end];
+    let mut v_cur = v_end;
+    while let Some(v_prev) = comes_from[v_cur] {
+        if v_prev == v_start {
+            break;
+        }
+        path.push(v_prev);
+        v_cur = v_prev;
+    }
+    path.reverse();
+
+    Some(path)
+}
+
+/// 経路上でよく通る区間が A 上で連続するように A を組み立てる.
+/// 経路で隣り合う都市の組を辺とみなして通過回数を数え, 通過回数の多い未使用の辺を貪欲に辿って鎖を作り,
+/// 鎖を順に連結する. 一度の信号操作で経路の先の区間をまとめて青にできる.
+/// 経路上の都市は必ず 1 回以上含め, 余った枠は経路上の都市を乱択で埋める.
+/// 通過回数には乱数で揺らぎを入れ, 呼び出しごとに異なる A を作る.
+fn build_a(n: usize, la: usize, paths: &[Vec<usize>], rng: &mut SmallRng) -> Vec<usize> {
+    let mut edge_cnt = HashMap::new();
+    let mut required = vec![false; n];
+    let mut v_prev = 0;
+    required[v_prev] = true;
+    for &v in paths.iter().flatten() {
+        *edge_cnt.entry((v_prev.min(v), v_prev.max(v))).or_insert(0) += 1;
+        required[v] = true;
+        v_prev = v;
+    }
+
+    let mut edge_list = edge_cnt
+        .into_iter()
+        .map(|((u, v), cnt)| (u, v, cnt as f64 * (1.0 + rng.gen::<f64>())))
+        .collect::<Vec<_>>();
+    edge_list.sort_by(|e0, e1| e1.2.total_cmp(&e0.2));
+    // 辺の番号は重み降順なので, 各都市の隣接リストも重み降順になる
+    let mut adj = vec![vec![]; n];
+    for (e, &(u, v, _)) in edge_list.iter().enumerate() {
+        adj[u].push((e, v));
+        adj[v].push((e, u));
+    }
+
+    let mut a = Vec::with_capacity(la);
+    let mut covered = vec![false; n];
+    let mut missing_cnt = required.iter().filter(|&&r| r).count();
+    let mut used = vec![false; edge_list.len()];
+    // 経路上の都市を全て入れる枠を残したまま `v` を追加できるか
+    let fits = |a: &Vec<usize>, covered: &[bool], missing_cnt: usize, v: usize| {
+        a.len() + 1 + missing_cnt - !covered[v] as usize <= la
+    };
+    'chain: for e_begin in 0..edge_list.len() {
+        if used[e_begin] {
+            continue;
+        }
+
+        let (u, v, _) = edge_list[e_begin];
+        if a.len() + 2 + missing_cnt - !covered[u] as usize - !covered[v] as usize > la {
+            break;
+        }
+
+        let mut v_cur = u;
+        a.push(u);
+        if !covered[u] {
+            covered[u] = true;
+            missing_cnt -= 1;
+        }
+        let mut e_cur = e_begin;
+        loop {
+            used[e_cur] = true;
+            let (x, y, _) = edge_list[e_cur];
+            let v_nxt = if x == v_cur { y } else { x };
+            a.push(v_nxt);
+            if !covered[v_nxt] {
+                covered[v_nxt] = true;
+                missing_cnt -= 1;
+            }
+            v_cur = v_nxt;
+
+            let Some(&(e_nxt, v_nxt)) = adj[v_cur].iter().find(|&&(e, _)| !used[e]) else {
+                continue 'chain;
+            };
+            if !fits(&a, &covered, missing_cnt, v_nxt) {
+                break 'chain;
+            }
+            e_cur = e_nxt;
+        }
+    }
+
+    let required = (0..n).filter(|&v| required[v]).collect::<Vec<_>>();
+    a.extend(required.iter().filter(|&&v| !covered[v]));
+    while a.len() < la {
+        a.push(required[rng.gen::<usize>() % required.len()]);
+    }
+
+    a
+}
+
+/// A を固定して旅行計画を先頭から貪欲に進め, 出力する操作列と信号操作の回数を返す.
+/// 各移動は現在の B を考慮した経路を通り, 赤信号の都市に着くたびに直近の経路を最も多く青にする書き換えを選ぶ.
+/// 遠回りで操作回数の上限を超えないよう, 残りの旅行を最短経路で進めても上限に収まる場合に限り遠回りする.
+fn plan(
+    a: &[usize],
+    lb: usize,
+    edges: &[Vec<usize>],
+    tt: &[usize],
+    paths: &[Vec<usize>],
+) -> (Vec<Operation>, usize) {
+    let n = edges.len();
+    let la = a.len();
+    let mut ans_cur = vec![];
+    let mut ans_score_cur = 0;
+
+    ans_cur.push(Operation::Init(a.to_vec()));
+    let mut v_to_a_idx = vec![vec![]; n];
+    for (i, &a) in a.iter().enumerate() {
+        v_to_a_idx[a].push(i);
+    }
+    let in_a = v_to_a_idx
+        .iter()
+        .map(|idx| !idx.is_empty())
+        .collect::<Vec<_>>();
+
+    // 青信号管理用の配列 B, 問題文準拠の初期値 -1 だと型変換が面倒だから別のダミー値にする
+    let mut b = vec![DUMMY; lb];
+    let mut b_idx = 0;
+    // 一つの信号を重複してもったほうがよい可能性がある
+    let mut open_cnt = vec![0; n];
+    let mut is_nearby = vec![false; n];
+    let mut operation_cnt = 0;
+    // i 番目以降の旅行を最短経路で進めたときの操作回数の上界, 1 回の移動につき信号操作と合わせて高々 2 回
+    let mut operation_cnt_rest = vec![0; tt.len() + 1];
+    for i in (0..tt.len()).rev() {
+        operation_cnt_rest[i] = operation_cnt_rest[i + 1] + 2 * paths[i].len();
+    }
+    let mut v_cur = 0;
+    for (i, &v_end) in tt.iter().enumerate() {
+        // 現在の B で青の都市を優先して経路を選ぶ, 見つからないか上限を超えうるなら最短経路を使う
+        let path_cur = signal_aware_path(edges, v_cur, v_end, &open_cnt, &in_a)
+            .filter(|path| {
+                operation_cnt + 2 * path.len() + operation_cnt_rest[i + 1] <= OPERATION_CNT_LIMIT
+            })
+            .unwrap_or_else(|| paths[i].clone());
+        v_cur = v_end;
+        for (path_cur_idx, &v_nxt) in path_cur.iter().enumerate() {
+            if open_cnt[v_nxt] > 0 {
+                ans_cur.push(Operation::MoveTo(v_nxt));
+                operation_cnt += 1;
+            } else {
+                // A の長さが最大 1200
+                // B の始点/終点の選び方が最大 24*23=552
+                // 愚直だと信号操作数/移動数が共に高々 10000 くらい？
+                // A の始点を次に行くマスに固定すると計算が絞れる, 一旦 1 にする
+                // 書き換える長さも高々 4 (L_B 下限) にしてしまえば,
+                // 10000*4*24 程度の計算回数になるので TLE は回避できるはず
+                // これに B の書き換え基準位置も 4 通りくらい見せて合計 4e6 くらい
+
+                // 効率判定及び信号変化に必要な情報は
+                // - A の使用開始地点
+                // - B の書き換え開始地点
+                // - 書き換えの長さ
+                // - 今の最高スコア (直近の行動予定の内, 書き換え後に通れるマスの数)
+                let mut a_idx_update = 0;
+                let mut b_idx_update = 0;
+                let mut update_len = 0;
+                let mut score_max = 0;
+                // 直近の経路に含まれる都市に印をつけ, 書き換えによる B 中の該当数の増減だけを数える
+                let window =
+                    &path_cur[path_cur_idx..(path_cur_idx + B_UPDATE_LEN_MAX).min(path_cur.len())];
+                for &v in window {
+                    is_nearby[v] = true;
+                }
+                let nearby = |v: usize| v != DUMMY && is_nearby[v];
+                let score_base = b.iter().filter(|&&bb| nearby(bb)).count();
+
+                let begin_ptrn_num = B_UPDATE_BEGIN_PTRN_NUM.min(lb);
+                for idx_diff in 0..begin_ptrn_num {
+                    let b_idx_begin = (lb + b_idx + idx_diff - begin_ptrn_num / 2) % lb;
+                    for &a_idx_begin in &v_to_a_idx[v_nxt] {
+                        // 書き換えの長さを 1 ずつ伸ばしながら差分でスコアを更新する
+                        let mut score_cur = score_base;
+                        for len in 1..=B_UPDATE_LEN_MAX {
+                            let b_idx_end = b_idx_begin + len - 1;
+                            let a_idx_end = a_idx_begin + len - 1;
+                            if b_idx_end >= lb || a_idx_end >= la {
+                                break;
+                            }
+
+                            score_cur += nearby(a[a_idx_end]) as usize;
+                            score_cur -= nearby(b[b_idx_end]) as usize;
+                            // 同点なら B を壊さないよう短い書き換えを優先する
+                            if score_cur > score_max || (score_cur == score_max && len < update_len)
+                            {
+                                a_idx_update = a_idx_begin;
+                                b_idx_update = b_idx_begin;
+                                update_len = len;
+                                score_max = score_cur;
+                            }
+                        }
+                    }
+                }
+                for &v in window {
+                    is_nearby[v] = false;
+                }
+
+                for i in 0..update_len {
+                    if b[b_idx_update + i] != DUMMY {
+                        open_cnt[b[b_idx_update + i]] -= 1;
+                    }
+
+                    b[b_idx_update + i] = a[a_idx_update + i];
+                    open_cnt[b[b_idx_update + i]] += 1;
+                }
+                b_idx = (b_idx + update_len + 1) % lb;
+
+                ans_cur.push(Operation::Signal(update_len, a_idx_update, b_idx_update));
+                ans_score_cur += 1;
+                b_idx = (b_idx + 1) % lb;
+                ans_cur.push(Operation::MoveTo(v_nxt));
+                operation_cnt += 2;
+            }
+            debug_assert!(operation_cnt <= OPERATION_CNT_LIMIT);
+        }
+    }
+
+    (ans_cur, ans_score_cur)
+}
+
+/// 与えられた A を初期解として, A を焼きなましで改善する.
+/// 近傍は 2 要素の交換, 1 要素の置換 (直前の都市の隣接都市を優先), 区間の複製の 3 種類で,
+/// 候補ごとに `plan` で旅行計画全体を貪欲に進めて信号操作の回数を評価値とする.
+/// 経路上の都市が A から消える近傍は, 通れない都市ができてしまうため採用しない.
+#[allow(clippy::too_many_arguments)]
+fn anneal_a(
+    mut a: Vec<usize>,
+    lb: usize,
+    edges: &[Vec<usize>],
+    tt: &[usize],
+    paths: &[Vec<usize>],
+    start_time: Instant,
+    break_time: Duration,
+    rng: &mut SmallRng,
+) -> (Vec<Operation>, usize) {
+    let n = edges.len();
+    let la = a.len();
+    let mut required = vec![false; n];
+    for &v in paths.iter().flatten() {
+        required[v] = true;
+    }
+    let required_list = (0..n).filter(|&v| required[v]).collect::<Vec<_>>();
+
+    let (mut ans, mut ans_score) = plan(&a, lb, edges, tt, paths);
+    let mut score_cur = ans_score;
+    let begin_time = start_time.elapsed();
+    let mut a_cnt = vec![0; n];
+    loop {
+        let elapsed = start_time.elapsed();
+        if elapsed >= break_time {
+            break;
+        }
+        let progress =
+            (elapsed - begin_time).as_secs_f64() / (break_time - begin_time).as_secs_f64();
+        let temp =
+            A_ANNEALING_TEMP_BEGIN * (A_ANNEALING_TEMP_END / A_ANNEALING_TEMP_BEGIN).powf(progress);
+
+        let mut a_nxt = a.clone();
+        match rng.gen::<usize>() % 3 {
+            0 => {
+                let i = rng.gen::<usize>() % la;
+                let j = rng.gen::<usize>() % la;
+                a_nxt.swap(i, j);
+            }
+            1 => {
+                let i = rng.gen::<usize>() % la;
+                let neighbors = if i > 0 {
+                    &edges[a[i - 1]]
+                } else {
+                    &edges[a[i + 1]]
+                };
+                let v = neighbors[rng.gen::<usize>() % neighbors.len()];
+                a_nxt[i] = if required[v] {
+                    v
+                } else {
+                    required_list[rng.gen::<usize>() % required_list.len()]
+                };
+            }
+            _ => {
+                let len = 2 + rng.gen::<usize>() % (lb - 1);
+                if la < len {
+                    continue;
+                }
+                let src = rng.gen::<usize>() % (la - len + 1);
+                let dst = rng.gen::<usize>() % (la - len + 1);
+                a_nxt.copy_within(src..src + len, dst);
+            }
+        }
+        if a_nxt == a {
+            continue;
+        }
+
+        a_cnt.fill(0);
+        for &v in &a_nxt {
+            a_cnt[v] += 1;
+        }
+        if required_list.iter().any(|&v| a_cnt[v] == 0) {
+            continue;
+        }
+
+        let (ans_nxt, score_nxt) = plan(&a_nxt, lb, edges, tt, paths);
+        let diff = score_nxt as f64 - score_cur as f64;
+        if diff <= 0.0 || rng.gen::<f64>() < (-diff / temp).exp() {
+            a = a_nxt;
+            score_cur = score_nxt;
+            if score_cur < ans_score {
+                ans = ans_nxt;
+                ans_score = score_cur;
+            }
+        }
+    }
+
+    (ans, ans_score)
+}
+
 #[fastout]
 fn main() {
     let start_time = Instant::now();
@@ -123,8 +493,6 @@ fn main() {
 
     // 最短経路を先に求める
     // 経路の確定は後回しにする, アルゴリズムを分けられる
-    // TODO: vec に一旦全部 push して sort して dedup して, の方が高速な可能性
-    let mut edges_used = HashSet::new();
     let mut paths = vec![vec![]; t];
     let mut v_start = 0;
     for (i, &v_end) in tt.iter().enumerate() {
@@ -156,10 +524,8 @@ fn main() {
 
         let mut path_cur = vec![v_end];
         let mut v_cur = v_end;
-        edges_used.insert(v_end);
         while let Some(v_prev) = comes_from[v_cur] {
             path_cur.push(v_prev);
-            edges_used.insert(v_prev);
             v_cur = v_prev;
         }
         // `pop`: 始点の情報が重複することを防ぐため
@@ -170,134 +536,34 @@ fn main() {
         v_start = v_end;
     }
     debug!("paths: {:?}", paths);
-    debug!("edges_used: {:?}", edges_used);
-    let edges_used = edges_used.into_iter().collect::<Vec<usize>>();
 
+    let resample_break_time = if A_ANNEALING {
+        break_time.mul_f64(A_RESAMPLE_TIME_RATIO)
+    } else {
+        break_time
+    };
+    let mut a_best = vec![];
     let mut ans = vec![];
     let mut ans_score = DUMMY;
 
-    while start_time.elapsed() < break_time {
-        let mut ans_cur = vec![];
-        let mut ans_score_cur = 0;
-
-        // 信号操作用の配列 A
-        let mut a = vec![0; la];
-        for i in 0..la {
-            a[i] = if i < edges_used.len() {
-                edges_used[i]
-            } else {
-                edges_used[rng.gen::<usize>() % edges_used.len()]
-            };
-        }
-        a.shuffle(&mut rng);
-        ans_cur.push(Operation::Init(a.clone()));
-        let mut v_to_a_idx = vec![vec![]; n];
-        for (i, &a) in a.iter().enumerate() {
-            v_to_a_idx[a].push(i);
-        }
-
-        // 青信号管理用の配列 B, 問題文準拠の初期値 -1 だと型変換が面倒だから別のダミー値にする
-        let mut b = vec![DUMMY; lb];
-        let mut b_idx = 0;
-        // 一つの信号を重複してもったほうがよい可能性がある
-        let mut open_cnt = vec![0; n];
-        let mut operation_cnt = 0;
-        for path_cur in &paths {
-            for (path_cur_idx, &v_nxt) in path_cur.iter().enumerate() {
-                if open_cnt[v_nxt] > 0 {
-                    ans_cur.push(Operation::MoveTo(v_nxt));
-                    operation_cnt += 1;
-                } else {
-                    // A の長さが最大 1200
-                    // B の始点/終点の選び方が最大 24*23=552
-                    // 愚直だと信号操作数/移動数が共に高々 10000 くらい？
-                    // A の始点を次に行くマスに固定すると計算が絞れる, 一旦 1 にする
-                    // 書き換える長さも高々 4 (L_B 下限) にしてしまえば,
-                    // 10000*4*24 程度の計算回数になるので TLE は回避できるはず
-                    // これに B の書き換え基準位置も 4 通りくらい見せて合計 4e6 くらい
-
-                    // 効率判定及び信号変化に必要な情報は
-                    // - A の使用開始地点
-                    // - B の書き換え開始地点
-                    // - 書き換えの長さ
-                    // - 今の最高スコア (直近の行動予定の内, 書き換え後に通れるマスの数)
-                    let mut a_idx_update = 0;
-                    let mut b_idx_update = 0;
-                    let mut update_len = 0;
-                    let mut score_max = 0;
-                    let mut v_nearby = HashSet::new();
-                    for i in 0..B_UPDATE_LEN_MAX {
-                        let j = path_cur_idx + i;
-                        if j >= path_cur.len() {
-                            break;
-                        }
-
-                        v_nearby.insert(path_cur[j]);
-                    }
-                    let v_nearby = v_nearby;
-
-                    for idx_diff in 0..B_UPDATE_BEGIN_PTRN_NUM {
-                        debug!("b_idx: {b_idx}, idx_diff: {idx_diff}");
-                        let b_idx_begin =
-                            (lb + b_idx + idx_diff - B_UPDATE_BEGIN_PTRN_NUM / 2) % lb;
-                        debug!("b_idx_begin: {b_idx_begin}");
-                        for idx_from_begin in 0..B_UPDATE_LEN_MAX {
-                            // additional_update_len + 1 個のマスを書き換える
-                            if b_idx_begin + idx_from_begin >= lb {
-                                continue;
-                            }
-
-                            for &a_idx_begin in &v_to_a_idx[v_nxt] {
-                                if a_idx_begin + idx_from_begin >= la {
-                                    continue;
-                                }
-
-                                // TODO: 連続した書き換えでありループを跨いで高速化できる
-                                let mut b_candidate = b.clone();
-                                for i in 0..idx_from_begin + 1 {
-                                    b_candidate[b_idx_begin + i] = a[a_idx_begin + i];
-                                }
-
-                                let mut score_cur = 0;
-                                for bb in &b_candidate {
-                                    if v_nearby.contains(bb) {
-                                        score_cur += 1;
-                                    }
-                                }
-                                if score_cur > score_max {
-                                    a_idx_update = a_idx_begin;
-                                    b_idx_update = b_idx_begin;
-                                    update_len = idx_from_begin + 1;
-                                    score_max = score_cur;
-                                }
-                            }
-                        }
-                    }
-
-                    for i in 0..update_len {
-                        if b[b_idx_update + i] != DUMMY {
-                            open_cnt[b[b_idx_update + i]] -= 1;
-                        }
-
-                        b[b_idx_update + i] = a[a_idx_update + i];
-                        open_cnt[b[b_idx_update + i]] += 1;
-                    }
-                    b_idx = (b_idx + update_len + 1) % lb;
-
-                    ans_cur.push(Operation::Signal(update_len, a_idx_update, b_idx_update));
-                    ans_score_cur += 1;
-                    b_idx = (b_idx + 1) % lb;
-                    ans_cur.push(Operation::MoveTo(v_nxt));
-                    operation_cnt += 2;
-                }
-                debug_assert!(operation_cnt <= OPERATION_CNT_LIMIT);
-            }
-        }
-
+    // 時間が足りなくても A を一つは作る
+    loop {
+        let a = build_a(n, la, &paths, &mut rng);
+        let (ans_cur, ans_score_cur) = plan(&a, lb, &edges, &tt, &paths);
         if ans_score_cur < ans_score {
+            a_best = a;
             ans = ans_cur;
             ans_score = ans_score_cur;
         }
+        if start_time.elapsed() >= resample_break_time {
+            break;
+        }
+    }
+
+    if A_ANNEALING {
+        (ans, _) = anneal_a(
+            a_best, lb, &edges, &tt, &paths, start_time, break_time, &mut rng,
+        );
     }
 
     for a in ans {
@@ -0,0 +1,166 @@
+// 出力された操作列を再生して検証し, 信号操作の回数をスコアとして出力する
+//
+// cargo run -r --bin checker <input> <output>
+
+use std::collections::HashSet;
+use std::process::exit;
+
+const OPERATION_CNT_LIMIT: usize = 100_000;
+
+/// 出力の 1 行を解釈したもの
+#[derive(Clone, Debug)]
+enum Operation {
+    Signal(usize, usize, usize),
+    MoveTo(usize),
+}
+
+fn parse_operation(line: &str) -> Result<Operation, String> {
+    let tokens = line.split_whitespace().collect::<Vec<_>>();
+    let parse = |s: &str| {
+        s.parse::<usize>()
+            .map_err(|_| format!("invalid number {s:?} in {line:?}"))
+    };
+    match tokens.as_slice() {
+        ["s", len, a_idx, b_idx] => Ok(Operation::Signal(
+            parse(len)?,
+            parse(a_idx)?,
+            parse(b_idx)?,
+        )),
+        ["m", v] => Ok(Operation::MoveTo(parse(v)?)),
+        _ => Err(format!("unknown operation {line:?}")),
+    }
+}
+
+struct Checker {
+    la: usize,
+    lb: usize,
+    edges: Vec<HashSet<usize>>,
+    tt: Vec<usize>,
+    a: Vec<usize>,
+    /// 青信号管理用の配列 B, 初期値は問題文の -1 の代わりに `None`
+    b: Vec<Option<usize>>,
+    v_cur: usize,
+    /// 次に訪れるべき `tt` の添字
+    tt_idx: usize,
+    signal_cnt: usize,
+    operation_cnt: usize,
+}
+
+impl Checker {
+    fn apply(&mut self, op: &Operation) -> Result<(), String> {
+        self.operation_cnt += 1;
+        if self.operation_cnt > OPERATION_CNT_LIMIT {
+            return Err(format!("more than {OPERATION_CNT_LIMIT} operations"));
+        }
+
+        match *op {
+            Operation::Signal(len, a_idx, b_idx) => {
+                if len == 0 || len > self.lb {
+                    return Err(format!("signal length {len} is out of [1, {}]", self.lb));
+                }
+                if a_idx + len > self.la {
+                    return Err(format!(
+                        "A[{a_idx}..{}] is out of length {}",
+                        a_idx + len,
+                        self.la
+                    ));
+                }
+                if b_idx + len > self.lb {
+                    return Err(format!(
+                        "B[{b_idx}..{}] is out of length {}",
+                        b_idx + len,
+                        self.lb
+                    ));
+                }
+                for i in 0..len {
+                    self.b[b_idx + i] = Some(self.a[a_idx + i]);
+                }
+                self.signal_cnt += 1;
+            }
+            Operation::MoveTo(v) => {
+                if !self.edges[self.v_cur].contains(&v) {
+                    return Err(format!("no road between {} and {v}", self.v_cur));
+                }
+                if !self.b.contains(&Some(v)) {
+                    return Err(format!("signal of {v} is red"));
+                }
+                self.v_cur = v;
+                if self.tt_idx < self.tt.len() && self.tt[self.tt_idx] == v {
+                    self.tt_idx += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() != 2 {
+        eprintln!("usage: checker <input> <output>");
+        exit(2);
+    }
+
+    let input = std::fs::read_to_string(&args[0]).expect("failed to read input");
+    let mut tokens = input
+        .split_whitespace()
+        .map(|tok| tok.parse::<isize>().expect("failed to parse isize"));
+    let mut next = || tokens.next().expect("input is too short") as usize;
+    let (n, m, t, la, lb) = (next(), next(), next(), next(), next());
+    let mut edges = vec![HashSet::new(); n];
+    for _ in 0..m {
+        let (u, v) = (next(), next());
+        edges[u].insert(v);
+        edges[v].insert(u);
+    }
+    let tt = (0..t).map(|_| next()).collect::<Vec<_>>();
+
+    let output = std::fs::read_to_string(&args[1]).expect("failed to read output");
+    let mut lines = output.lines().filter(|line| !line.trim().is_empty());
+    let Some(a_line) = lines.next() else {
+        eprintln!("output is empty");
+        exit(1);
+    };
+    let a = match a_line
+        .split_whitespace()
+        .map(|tok| tok.parse::<usize>().ok().filter(|&v| v < n))
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(a) if a.len() == la => a,
+        _ => {
+            eprintln!("A must be {la} cities in [0, {n})");
+            exit(1);
+        }
+    };
+
+    let mut checker = Checker {
+        la,
+        lb,
+        edges,
+        tt,
+        a,
+        b: vec![None; lb],
+        v_cur: 0,
+        tt_idx: 0,
+        signal_cnt: 0,
+        operation_cnt: 0,
+    };
+    for (line_idx, line) in lines.enumerate() {
+        if let Err(e) = parse_operation(line).and_then(|op| checker.apply(&op)) {
+            // A の行を含めて 1-origin の行番号
+            eprintln!("line {}: {e}", line_idx + 2);
+            exit(1);
+        }
+    }
+
+    if checker.tt_idx != t {
+        eprintln!(
+            "travel plan is not finished: visited {} of {t} cities",
+            checker.tt_idx
+        );
+        exit(1);
+    }
+    println!("Operations = {}", checker.operation_cnt);
+    println!("Score = {}", checker.signal_cnt);
+}
@@ -0,0 +1,193 @@
+// 都市と道路を座標通りに描き, 出力の経路と指定した操作時点の青信号を SVG で出力する
+//
+// cargo run -r --bin vis <input> <output> [operation_idx] > out.svg
+//
+// `operation_idx` は A の行を除いた 0-origin の操作番号で, その操作を終えた直後の状態を描く.
+// 省略すると全ての操作を終えた状態を描く.
+// - 灰色の線: 道路
+// - 薄い青の線: 出力の経路全体, 濃い青の線: 指定時点までに通った経路
+// - 緑の点: 指定時点で B に含まれる都市, 赤の点: 現在地, 橙の枠: 次の目的地
+
+use std::fmt::Write;
+use std::process::exit;
+
+/// SVG の一辺の長さ, 入力の座標は [0, 1000]
+const SIZE: f64 = 1000.0;
+const MARGIN: f64 = 10.0;
+
+#[derive(Clone, Debug)]
+enum Operation {
+    Signal(usize, usize, usize),
+    MoveTo(usize),
+}
+
+fn parse_operation(line: &str) -> Option<Operation> {
+    let tokens = line.split_whitespace().collect::<Vec<_>>();
+    let parse = |s: &str| s.parse::<usize>().ok();
+    match tokens.as_slice() {
+        ["s", len, a_idx, b_idx] => {
+            Some(Operation::Signal(parse(len)?, parse(a_idx)?, parse(b_idx)?))
+        }
+        ["m", v] => Some(Operation::MoveTo(parse(v)?)),
+        _ => None,
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() != 2 && args.len() != 3 {
+        eprintln!("usage: vis <input> <output> [operation_idx]");
+        exit(2);
+    }
+
+    let input = std::fs::read_to_string(&args[0]).expect("failed to read input");
+    let mut tokens = input
+        .split_whitespace()
+        .map(|tok| tok.parse::<isize>().expect("failed to parse isize"));
+    let mut next = || tokens.next().expect("input is too short");
+    let (n, m, t, _la, lb) = (
+        next() as usize,
+        next() as usize,
+        next() as usize,
+        next() as usize,
+        next() as usize,
+    );
+    let uvm = (0..m)
+        .map(|_| (next() as usize, next() as usize))
+        .collect::<Vec<_>>();
+    let tt = (0..t).map(|_| next() as usize).collect::<Vec<_>>();
+    let xyn = (0..n)
+        .map(|_| (next() as f64, next() as f64))
+        .collect::<Vec<_>>();
+
+    let output = std::fs::read_to_string(&args[1]).expect("failed to read output");
+    let mut lines = output.lines().filter(|line| !line.trim().is_empty());
+    let a = lines
+        .next()
+        .map(|line| {
+            line.split_whitespace()
+                .map(|tok| tok.parse::<usize>().expect("failed to parse A"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let ops = lines
+        .enumerate()
+        .map(|(i, line)| {
+            parse_operation(line).unwrap_or_else(|| {
+                eprintln!("line {}: unknown operation {line:?}", i + 2);
+                exit(1);
+            })
+        })
+        .collect::<Vec<_>>();
+    let op_end = match args.get(2) {
+        Some(s) => s.parse::<usize>().expect("invalid operation_idx") + 1,
+        None => ops.len(),
+    }
+    .min(ops.len());
+
+    // 全経路と, 指定時点の状態を再生する
+    let mut route = vec![0];
+    let mut route_len_at_end = 1;
+    let mut b = vec![None; lb];
+    let mut tt_idx = 0;
+    for (op_idx, op) in ops.iter().enumerate() {
+        match *op {
+            Operation::Signal(len, a_idx, b_idx) => {
+                if op_idx < op_end {
+                    for i in 0..len {
+                        b[b_idx + i] = Some(a[a_idx + i]);
+                    }
+                }
+            }
+            Operation::MoveTo(v) => {
+                route.push(v);
+                if op_idx < op_end {
+                    route_len_at_end = route.len();
+                    if tt_idx < t && tt[tt_idx] == v {
+                        tt_idx += 1;
+                    }
+                }
+            }
+        }
+    }
+    let v_cur = route[route_len_at_end - 1];
+
+    let scale = (SIZE - 2.0 * MARGIN) / SIZE;
+    let pos = |v: usize| {
+        let (x, y) = xyn[v];
+        (MARGIN + x * scale, MARGIN + y * scale)
+    };
+    let polyline = |route: &[usize], color: &str, width: f64| {
+        let points = route
+            .iter()
+            .map(|&v| {
+                let (x, y) = pos(v);
+                format!("{x:.1},{y:.1}")
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!(
+            "<polyline points=\"{points}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"{width}\" />\n"
+        )
+    };
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {SIZE} {SIZE}\" width=\"{SIZE}\" height=\"{SIZE}\">"
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<rect x=\"0\" y=\"0\" width=\"{SIZE}\" height=\"{SIZE}\" fill=\"white\" />"
+    )
+    .unwrap();
+    for &(u, v) in &uvm {
+        let ((x0, y0), (x1, y1)) = (pos(u), pos(v));
+        writeln!(
+            svg,
+            "<line x1=\"{x0:.1}\" y1=\"{y0:.1}\" x2=\"{x1:.1}\" y2=\"{y1:.1}\" stroke=\"lightgray\" stroke-width=\"1\" />"
+        )
+        .unwrap();
+    }
+    svg.push_str(&polyline(&route, "lightblue", 1.5));
+    svg.push_str(&polyline(&route[..route_len_at_end], "royalblue", 2.0));
+
+    let mut is_green = vec![false; n];
+    for v in b.iter().flatten() {
+        is_green[*v] = true;
+    }
+    for (v, &green) in is_green.iter().enumerate() {
+        let (x, y) = pos(v);
+        let (color, r) = if v == v_cur {
+            ("red", 6.0)
+        } else if green {
+            ("limegreen", 5.0)
+        } else {
+            ("gray", 2.5)
+        };
+        writeln!(
+            svg,
+            "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"{r}\" fill=\"{color}\"><title>{v}</title></circle>"
+        )
+        .unwrap();
+    }
+    if tt_idx < t {
+        let (x, y) = pos(tt[tt_idx]);
+        writeln!(
+            svg,
+            "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"9\" fill=\"none\" stroke=\"orange\" stroke-width=\"3\" />"
+        )
+        .unwrap();
+    }
+    writeln!(
+        svg,
+        "<text x=\"{MARGIN}\" y=\"{}\" font-size=\"16\">operation {op_end}/{}, visited {tt_idx}/{t}</text>",
+        SIZE - MARGIN,
+        ops.len()
+    )
+    .unwrap();
+    svg.push_str("</svg>\n");
+
+    print!("{svg}");
+}
@@ -4,7 +4,7 @@ use proconio::input;
 use proconio::marker::Chars;
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
-use std::collections::{HashSet, VecDeque};
+use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
 // 固定
@@ -12,7 +12,7 @@ const N: usize = 30;
 const M: usize = 10;
 const K: usize = 10;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Operation {
     L,
     R,
@@ -34,6 +34,14 @@ impl std::fmt::Display for Operation {
 }
 
 impl Operation {
+    const ALL: [Operation; 5] = [
+        Operation::L,
+        Operation::R,
+        Operation::U,
+        Operation::D,
+        Operation::S,
+    ];
+
     fn dir(&self) -> (isize, isize) {
         match self {
             Operation::L => (0, -1),
@@ -43,6 +51,16 @@ impl Operation {
             Operation::S => (0, 0),
         }
     }
+
+    fn idx(&self) -> usize {
+        match self {
+            Operation::L => 0,
+            Operation::R => 1,
+            Operation::U => 2,
+            Operation::D => 3,
+            Operation::S => 4,
+        }
+    }
 }
 
 fn could_move(
@@ -84,30 +102,75 @@ fn move_pos(
     (ni, nj)
 }
 
-fn shortest_path(
-    vbegin: (usize, usize),
-    vn: &Vec<Vec<char>>,
-    hn: &Vec<Vec<char>>,
-) -> Vec<Vec<Vec<Operation>>> {
-    let ops = [Operation::L, Operation::R, Operation::U, Operation::D];
-    let mut ret = vec![vec![vec![]; N]; N];
-    let mut que = VecDeque::new();
-    que.push_back(vbegin);
-    while let Some(vcur) = que.pop_front() {
-        for op in &ops {
-            let vnext = move_pos(vcur, op.dir(), vn, hn);
-            if vnext == vcur || vnext == vbegin || !ret[vnext.0][vnext.1].is_empty() {
-                continue;
-            }
+/// 全マス対全マスの最短距離と最短経路の最初の一手を, マス番号 i * N + j の組で引ける平坦な配列でもつ
+/// 経路そのものはもたず, 必要なときに最初の一手を辿って復元する
+struct ShortestPaths {
+    dist: Vec<u16>,
+    first_move: Vec<Operation>,
+}
+
+impl ShortestPaths {
+    const UNREACHABLE: u16 = u16::MAX;
+
+    fn new(vn: &Vec<Vec<char>>, hn: &Vec<Vec<char>>) -> Self {
+        let ops = [Operation::L, Operation::R, Operation::U, Operation::D];
+        let mut dist = vec![Self::UNREACHABLE; N * N * N * N];
+        let mut first_move = vec![Operation::S; N * N * N * N];
+        let mut que = VecDeque::new();
+        for vbegin in (0..N).cartesian_product(0..N) {
+            let base = Self::idx(vbegin, (0, 0));
+            dist[base + vbegin.0 * N + vbegin.1] = 0;
+            que.push_back(vbegin);
+            while let Some(vcur) = que.pop_front() {
+                let cur = base + vcur.0 * N + vcur.1;
+                for op in &ops {
+                    let vnext = move_pos(vcur, op.dir(), vn, hn);
+                    let next = base + vnext.0 * N + vnext.1;
+                    if dist[next] != Self::UNREACHABLE {
+                        continue;
+                    }
 
-            let mut pathnext = ret[vcur.0][vcur.1].clone();
-            pathnext.push(*op);
-            ret[vnext.0][vnext.1] = pathnext;
-            que.push_back(vnext);
+                    dist[next] = dist[cur] + 1;
+                    // 始点の隣ならその一手, それ以外は直前のマスへの最初の一手を引き継ぐ
+                    first_move[next] = if vcur == vbegin { *op } else { first_move[cur] };
+                    que.push_back(vnext);
+                }
+            }
         }
+
+        Self { dist, first_move }
     }
 
-    ret
+    fn idx(vfrom: (usize, usize), vto: (usize, usize)) -> usize {
+        ((vfrom.0 * N + vfrom.1) * N + vto.0) * N + vto.1
+    }
+
+    fn dist(&self, vfrom: (usize, usize), vto: (usize, usize)) -> usize {
+        self.dist[Self::idx(vfrom, vto)] as usize
+    }
+
+    /// `vfrom` から `vto` への最短経路の操作列, 到達できなければ空
+    fn path(
+        &self,
+        vfrom: (usize, usize),
+        vto: (usize, usize),
+        vn: &Vec<Vec<char>>,
+        hn: &Vec<Vec<char>>,
+    ) -> Vec<Operation> {
+        if self.dist[Self::idx(vfrom, vto)] == Self::UNREACHABLE {
+            return vec![];
+        }
+
+        let mut ret = Vec::with_capacity(self.dist(vfrom, vto));
+        let mut vcur = vfrom;
+        while vcur != vto {
+            let op = self.first_move[Self::idx(vcur, vto)];
+            ret.push(op);
+            vcur = move_pos(vcur, op.dir(), vn, hn);
+        }
+
+        ret
+    }
 }
 
 fn goal_order(vbegin: (usize, usize), dir: (isize, isize)) -> Option<Vec<(usize, usize)>> {
@@ -162,14 +225,425 @@ fn goal_order(vbegin: (usize, usize), dir: (isize, isize)) -> Option<Vec<(usize,
     Some(ret)
 }
 
+/// 全てのロボットが L/R/U/D それぞれに動けるボタンを一つ以上もつか
+fn is_controllable(buttons: &[Vec<Operation>]) -> bool {
+    (0..M).all(|j| {
+        Operation::ALL[..4]
+            .iter()
+            .all(|op| buttons.iter().any(|button| button[j] == *op))
+    })
+}
+
+/// 外周から内側へ渦巻き状に回る訪問順, 内部の壁は考えない
+/// 各周は `vbegin` に最も近いマスから始めて, `clockwise` なら時計回り, そうでなければ反時計回りに一周する
+fn spiral_order(vbegin: (usize, usize), clockwise: bool) -> Vec<(usize, usize)> {
+    let mut ret = Vec::with_capacity(N * N);
+    for r in 0..N.div_ceil(2) {
+        let (lo, hi) = (r, N - 1 - r);
+        // (lo, lo) から時計回りに一周
+        let mut ring = vec![];
+        if lo == hi {
+            ring.push((lo, lo));
+        } else {
+            ring.extend((lo..hi).map(|j| (lo, j)));
+            ring.extend((lo..hi).map(|i| (i, hi)));
+            ring.extend((lo + 1..=hi).rev().map(|j| (hi, j)));
+            ring.extend((lo + 1..=hi).rev().map(|i| (i, lo)));
+        }
+        if !clockwise {
+            ring.reverse();
+        }
+
+        let vnear = (vbegin.0.clamp(lo, hi), vbegin.1.clamp(lo, hi));
+        let idx_begin = ring.iter().position(|&v| v == vnear).unwrap();
+        ring.rotate_left(idx_begin);
+        ret.extend(ring);
+    }
+
+    ret
+}
+
+/// 時計回りに並べた上, 右, 下, 左の向き
+const DIRS_CW: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
+
+/// `vbegin` から BFS で最も近い, 壁 (盤の外周を含む) に接するマスと, 壁のある向きの `DIRS_CW` の添字
+fn nearest_wall(
+    vbegin: (usize, usize),
+    vn: &Vec<Vec<char>>,
+    hn: &Vec<Vec<char>>,
+) -> ((usize, usize), Vec<usize>) {
+    let mut visited = [[false; N]; N];
+    let mut que = VecDeque::new();
+    visited[vbegin.0][vbegin.1] = true;
+    que.push_back(vbegin);
+    while let Some(v) = que.pop_front() {
+        let walls = (0..4)
+            .filter(|&d| !could_move(v, DIRS_CW[d], vn, hn))
+            .collect::<Vec<_>>();
+        if !walls.is_empty() {
+            return (v, walls);
+        }
+
+        for dij in DIRS_CW {
+            let u = move_pos(v, dij, vn, hn);
+            if !visited[u.0][u.1] {
+                visited[u.0][u.1] = true;
+                que.push_back(u);
+            }
+        }
+    }
+
+    unreachable!("the board always has the outer wall")
+}
+
+/// 壁伝いに進む訪問順
+/// `vbegin` から `DIRS_CW[heading]` の向きに歩き始め, `right_hand` なら右手, そうでなければ左手を壁につけたまま進む
+/// 行き止まりでは未訪問のマスへ分岐できるところまで戻る (手の側を優先した深さ優先探索の行きがけ順)
+/// 後ろ向きも最後に試すので, 開始マスの背後も含めて到達できる全マスを返す
+fn wall_follow_order(
+    vbegin: (usize, usize),
+    heading: usize,
+    right_hand: bool,
+    vn: &Vec<Vec<char>>,
+    hn: &Vec<Vec<char>>,
+) -> Vec<(usize, usize)> {
+    // 進行方向に対して, 手の側, 直進, 手と逆の側, 後ろの順に試す
+    let turns = if right_hand {
+        [1, 0, 3, 2]
+    } else {
+        [3, 0, 1, 2]
+    };
+
+    let mut visited = [[false; N]; N];
+    let mut ret = Vec::with_capacity(N * N);
+    visited[vbegin.0][vbegin.1] = true;
+    ret.push(vbegin);
+    let mut stack = vec![(vbegin, heading)];
+    while let Some(&(v, h)) = stack.last() {
+        let nxt = turns.iter().map(|t| (h + t) % 4).find_map(|d| {
+            let u = move_pos(v, DIRS_CW[d], vn, hn);
+            (!visited[u.0][u.1]).then_some((u, d))
+        });
+        match nxt {
+            Some((u, d)) => {
+                visited[u.0][u.1] = true;
+                ret.push(u);
+                stack.push((u, d));
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+
+    ret
+}
+
+const TURN_MAX: usize = 2 * N * N;
+
+/// 目標を選ぶ直前のシミュレーションの状態
+#[derive(Clone, Copy)]
+struct Checkpoint {
+    robots_pos: [(usize, usize); M],
+    visited: [bool; N * N],
+    unvisited_cnt: usize,
+    operations_len: usize,
+    /// 次に調べる訪問順の添字
+    idx_goal: usize,
+    /// この状態に至るまでに読んだ訪問順の長さ, これより後ろの訪問順を変えてもこの状態は変わらない
+    order_read_len: usize,
+}
+
+/// ボタン割り当て `buttons` と訪問順 `goal_order` で全ロボットを動かした結果
+/// 未訪問の目標マスに最も近いロボットを最短経路で動かす
+/// 各手ではそのロボットを目的の向きに動かすボタンのうち, 全ロボットで新しく訪問するマスが最も多いものを押す
+/// 訪問順の途中から再開できるように, 目標を選ぶたびの状態を `checkpoints` に記録する
+#[derive(Clone)]
+struct Simulation {
+    checkpoints: Vec<Checkpoint>,
+    operations: Vec<usize>,
+    score: usize,
+}
+
+impl Simulation {
+    fn new(
+        buttons: &[Vec<Operation>],
+        goal_order: &[(usize, usize)],
+        ijm: &[(usize, usize)],
+        shortest_paths: &ShortestPaths,
+        vn: &Vec<Vec<char>>,
+        hn: &Vec<Vec<char>>,
+    ) -> Self {
+        let mut initial = Checkpoint {
+            robots_pos: [(0, 0); M],
+            visited: [false; N * N],
+            unvisited_cnt: N * N,
+            operations_len: 0,
+            idx_goal: 0,
+            order_read_len: 0,
+        };
+        for (j, &(i_begin, j_begin)) in ijm.iter().enumerate() {
+            initial.robots_pos[j] = (i_begin, j_begin);
+            if !initial.visited[i_begin * N + j_begin] {
+                initial.visited[i_begin * N + j_begin] = true;
+                initial.unvisited_cnt -= 1;
+            }
+        }
+
+        let mut ret = Self {
+            checkpoints: vec![initial],
+            operations: vec![],
+            score: 0,
+        };
+        ret.run(buttons, goal_order, shortest_paths, vn, hn);
+        ret
+    }
+
+    /// `base` のうち訪問順の `changed_idx` 番目以降に依存しない部分を引き継ぎ, 残りをシミュレーションし直す
+    #[allow(clippy::too_many_arguments)]
+    fn resume(
+        &mut self,
+        base: &Simulation,
+        changed_idx: usize,
+        buttons: &[Vec<Operation>],
+        goal_order: &[(usize, usize)],
+        shortest_paths: &ShortestPaths,
+        vn: &Vec<Vec<char>>,
+        hn: &Vec<Vec<char>>,
+    ) {
+        // 初期状態は訪問順を読んでいないので, 必ず 1 つ以上引き継げる
+        let checkpoint_cnt = base
+            .checkpoints
+            .partition_point(|cp| cp.order_read_len <= changed_idx);
+        self.checkpoints.clear();
+        self.checkpoints
+            .extend_from_slice(&base.checkpoints[..checkpoint_cnt]);
+        let operations_len = self.checkpoints[checkpoint_cnt - 1].operations_len;
+        self.operations.clear();
+        self.operations
+            .extend_from_slice(&base.operations[..operations_len]);
+        self.run(buttons, goal_order, shortest_paths, vn, hn);
+    }
+
+    /// 最後の checkpoint からシミュレーションを最後まで進める
+    fn run(
+        &mut self,
+        buttons: &[Vec<Operation>],
+        goal_order: &[(usize, usize)],
+        shortest_paths: &ShortestPaths,
+        vn: &Vec<Vec<char>>,
+        hn: &Vec<Vec<char>>,
+    ) {
+        // dir_buttons[j][d]: ロボット j を向き d に動かすボタンの一覧
+        let mut dir_buttons = vec![vec![vec![]; 4]; M];
+        for (b, button) in buttons.iter().enumerate() {
+            for (j, op) in button.iter().enumerate() {
+                if *op != Operation::S {
+                    dir_buttons[j][op.idx()].push(b);
+                }
+            }
+        }
+
+        let mut state = self.checkpoints.pop().unwrap();
+        loop {
+            self.checkpoints.push(state);
+            if state.unvisited_cnt == 0 || self.operations.len() >= TURN_MAX {
+                break;
+            }
+
+            // 目標位置を決める, 訪問順を使い切ったら未訪問のマスが残っていても打ち切る
+            while state.idx_goal < goal_order.len()
+                && state.visited[goal_order[state.idx_goal].0 * N + goal_order[state.idx_goal].1]
+            {
+                state.idx_goal += 1;
+            }
+            if state.idx_goal == goal_order.len() {
+                break;
+            }
+            state.order_read_len = state.idx_goal + 1;
+            let vgoal = goal_order[state.idx_goal];
+
+            // 目標に最も近いロボットを求める
+            let robot = (0..M)
+                .min_by_key(|&j| shortest_paths.dist(state.robots_pos[j], vgoal))
+                .unwrap();
+
+            for cur_op in shortest_paths.path(state.robots_pos[robot], vgoal, vn, hn) {
+                // 同じ向きに動かせるボタンのうち, 新しく訪問するマスが最も多いものを選ぶ
+                let &button = dir_buttons[robot][cur_op.idx()]
+                    .iter()
+                    .max_by_key(|&&b| {
+                        let newly_visited = (0..M)
+                            .filter(|&j| {
+                                let (ii, jj) =
+                                    move_pos(state.robots_pos[j], buttons[b][j].dir(), vn, hn);
+                                !state.visited[ii * N + jj]
+                            })
+                            .count();
+                        // 同点なら番号の小さいボタンを選ぶ
+                        (newly_visited, std::cmp::Reverse(b))
+                    })
+                    .unwrap();
+                self.operations.push(button);
+
+                // ロボット現在位置の更新
+                for (pos, op) in state.robots_pos.iter_mut().zip(&buttons[button]) {
+                    *pos = move_pos(*pos, op.dir(), vn, hn);
+                    let (ii, jj) = *pos;
+                    if !state.visited[ii * N + jj] {
+                        state.visited[ii * N + jj] = true;
+                        state.unvisited_cnt -= 1;
+                    }
+                }
+
+                if self.operations.len() == TURN_MAX {
+                    // 手数超過により強制終了
+                    break;
+                }
+            }
+            state.operations_len = self.operations.len();
+        }
+
+        self.score = if state.unvisited_cnt == 0 {
+            3 * N * N - self.operations.len()
+        } else {
+            N * N - state.unvisited_cnt
+        };
+    }
+}
+
+/// ボタン割り当てを固定して, `goal_order` を焼きなます
+/// 近傍は次の 3 種類で, 変更箇所より前の checkpoint からシミュレーションを再開して評価する
+/// - 訪問順の区間の反転
+/// - あるマスを, そのマスを目標に選ぶ時点で最も近い未訪問のマスの直後へ移動
+/// - 壁 (盤の端を含む) に接するマスが連続する区間を, 別の位置へ移動
+#[allow(clippy::too_many_arguments)]
+fn anneal_goal_order(
+    buttons: &[Vec<Operation>],
+    mut goal_order: Vec<(usize, usize)>,
+    ijm: &[(usize, usize)],
+    shortest_paths: &ShortestPaths,
+    vn: &Vec<Vec<char>>,
+    hn: &Vec<Vec<char>>,
+    break_time: Duration,
+    start_time: Instant,
+    rng: &mut SmallRng,
+) -> Simulation {
+    const TEMP_BEGIN: f64 = 3.0;
+    const TEMP_END: f64 = 0.3;
+    const REVERSE_LEN_MAX: usize = 30;
+    const BLOCK_LEN_MAX: usize = 30;
+
+    let ops = [Operation::L, Operation::R, Operation::U, Operation::D];
+    let cells = (0..N).cartesian_product(0..N).collect::<Vec<_>>();
+    let neighbors = cells
+        .iter()
+        .map(|&v| {
+            ops.iter()
+                .map(|op| move_pos(v, op.dir(), vn, hn))
+                .filter(|&u| u != v)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let is_wall_side = neighbors.iter().map(|nb| nb.len() < 4).collect::<Vec<_>>();
+
+    let mut sim_cur = Simulation::new(buttons, &goal_order, ijm, shortest_paths, vn, hn);
+    let mut sim_nxt = sim_cur.clone();
+    let mut sim_best = sim_cur.clone();
+    let begin_time = start_time.elapsed();
+    while start_time.elapsed() < break_time {
+        let progress = (start_time.elapsed() - begin_time).as_secs_f64()
+            / (break_time - begin_time).as_secs_f64();
+        let temp = TEMP_BEGIN * (TEMP_END / TEMP_BEGIN).powf(progress);
+
+        let len = goal_order.len();
+        let mut order_nxt = goal_order.clone();
+        let changed_idx = match rng.gen::<usize>() % 3 {
+            0 => {
+                let seg_len = rng.gen::<usize>() % (REVERSE_LEN_MAX - 1) + 2;
+                let i = rng.gen::<usize>() % (len - seg_len + 1);
+                order_nxt[i..i + seg_len].reverse();
+                i
+            }
+            1 => {
+                let i = rng.gen::<usize>() % len;
+                let v = order_nxt[i];
+                // i 番目の目標を選ぶ時点の訪問状況で, v から最短距離が最も近い未訪問のマスを探す
+                let cp_idx = sim_cur
+                    .checkpoints
+                    .partition_point(|cp| cp.idx_goal <= i)
+                    .saturating_sub(1);
+                let visited = &sim_cur.checkpoints[cp_idx].visited;
+                let Some(u) = cells
+                    .iter()
+                    .copied()
+                    .filter(|&u| u != v && !visited[u.0 * N + u.1])
+                    .min_by_key(|&u| shortest_paths.dist(v, u))
+                else {
+                    continue;
+                };
+                let p = order_nxt.iter().position(|&w| w == u).unwrap();
+                let v = order_nxt.remove(i);
+                if p < i {
+                    order_nxt.insert(p + 1, v);
+                    p + 1
+                } else {
+                    order_nxt.insert(p, v);
+                    i
+                }
+            }
+            _ => {
+                let i = rng.gen::<usize>() % len;
+                let mut j = i;
+                while j < len
+                    && j - i < BLOCK_LEN_MAX
+                    && is_wall_side[order_nxt[j].0 * N + order_nxt[j].1]
+                {
+                    j += 1;
+                }
+                if j == i {
+                    continue;
+                }
+                let block = order_nxt.drain(i..j).collect::<Vec<_>>();
+                let dst = rng.gen::<usize>() % (order_nxt.len() + 1);
+                order_nxt.splice(dst..dst, block);
+                i.min(dst)
+            }
+        };
+        if order_nxt == goal_order {
+            continue;
+        }
+
+        sim_nxt.resume(
+            &sim_cur,
+            changed_idx,
+            buttons,
+            &order_nxt,
+            shortest_paths,
+            vn,
+            hn,
+        );
+        let diff = sim_nxt.score as f64 - sim_cur.score as f64;
+        if diff >= 0.0 || rng.gen::<f64>() < (diff / temp).exp() {
+            goal_order = order_nxt;
+            std::mem::swap(&mut sim_cur, &mut sim_nxt);
+            if sim_cur.score > sim_best.score {
+                sim_best = sim_cur.clone();
+            }
+        }
+    }
+
+    sim_best
+}
+
 #[fastout]
 fn main() {
-    const TURN_MAX: usize = 2 * 30 * 30;
-
     // < 2 sec
     const RUN_TIME_MAX_MS: u64 = 1930;
-    const SWAP_COUNT_MAX: usize = 10;
-    const ORDER_CANDIDATE_NUM: usize = 4;
+    // 残りの時間で訪問順を焼きなます
+    const BUTTON_SEARCH_TIME_RATIO: f64 = 0.3;
+    const BUTTON_CHANGE_COUNT_MAX: usize = 3;
+    const BUTTON_CANDIDATE_NUM: usize = 4;
 
     let start_time = Instant::now();
     let break_time = Duration::from_millis(RUN_TIME_MAX_MS);
@@ -212,12 +686,8 @@ fn main() {
     // なんでもかんでも乱択はよくない
 
     // TODO: サイズ固定で高速化になる部分がありそう
-    let mut ans_score = 0;
     // c[i][j]: i 番目のボタン押下時のロボット j の動作
-    let mut ans_button = vec![vec![Operation::S; M]; K];
-    let mut ans_operation = vec![];
-    let mut ans_order = vec![];
-
+    // ボタン 0..4 を全ロボット共通の L/R/U/D とした割り当てから始めて, 割り当て全体を山登りする
     let mut buttons = vec![vec![Operation::S; M]; K];
     for i in 0..M {
         buttons[0][i] = Operation::L;
@@ -225,27 +695,9 @@ fn main() {
         buttons[2][i] = Operation::U;
         buttons[3][i] = Operation::D;
     }
-    let op_to_num = |op: Operation| match op {
-        Operation::L => 0,
-        Operation::R => 1,
-        Operation::U => 2,
-        Operation::D => 3,
-        _ => unreachable!(),
-    };
-
-    let mut operations: Vec<usize> = vec![];
-    let mut robots_pos = vec![(0, 0); m];
-    let mut unvisited = HashSet::new();
-    let mut visited = vec![vec![false; N]; N];
 
-    // 処理時間よい？
-    let mut shortest_paths = vec![vec![vec![vec![]; n]; n]; n];
-    for i in 0..N {
-        for j in 0..N {
-            shortest_paths[i][j] = shortest_path((i, j), &vn, &hn);
-        }
-    }
-    // debug で 822 ms, release で 170 ms くらいかかっている, 遅い
+    let shortest_paths = ShortestPaths::new(&vn, &hn);
+    // 経路を全部もっていたときは release で 170 ms くらいかかっていた
     // println!("{:?}", start_time.elapsed());
     // return;
 
@@ -259,124 +711,116 @@ fn main() {
             }
         }
     }
-    // ここまで 160 ms くらい, つまりほとんど時間くってない
+    // 四隅から時計回り/反時計回りに渦巻き状に内側へ進む訪問順
+    for vbegin in vbegins {
+        for clockwise in [true, false] {
+            goal_orders.push(spiral_order(vbegin, clockwise));
+        }
+    }
+    // 各ロボットから最寄りの壁 (内部の壁を含む) へつけ, 壁のある側を右手/左手にして壁伝いに進む訪問順
+    for &vrobot in &ijm {
+        let (vbegin, walls) = nearest_wall(vrobot, &vn, &hn);
+        for wall in walls {
+            for right_hand in [true, false] {
+                // 壁が手の側に来る向きに歩き始める
+                let heading = if right_hand {
+                    (wall + 3) % 4
+                } else {
+                    (wall + 1) % 4
+                };
+                goal_orders.push(wall_follow_order(vbegin, heading, right_hand, &vn, &hn));
+            }
+        }
+    }
+    // ここまで release で 30 ms くらい
     // println!("{:?}", start_time.elapsed());
     // return;
 
-    while start_time.elapsed() < break_time {
-        for goal_order in &goal_orders {
-            // 変数初期化
-            // ボタン割り当てを決めつけ
-            operations = vec![];
-            unvisited.clear();
-            for i in 0..N {
-                for j in 0..N {
-                    unvisited.insert((i, j));
-                }
-            }
-            for i in 0..m {
-                robots_pos[i] = (ijm[i].0, ijm[i].1);
-            }
-            for i in 0..N {
-                for j in 0..N {
-                    visited[i][j] = false;
-                }
-            }
-            for &(i, j) in &ijm {
-                unvisited.remove(&(i, j));
-                visited[i][j] = true;
-            }
-
-            // println!("goal_order: {goal_order:?}");
-            let mut idx_goal = 0;
-            let mut turn_current = 0;
-            while !unvisited.is_empty() && turn_current < TURN_MAX {
-                // println!("  turn: {turn_current}/{TURN_MAX}");
-
-                // 目標位置を決める
-                // println!("  idx_goal: {idx_goal}");
-                // println!("  unvisited: {unvisited:?}");
-                while visited[goal_order[idx_goal].0][goal_order[idx_goal].1] {
-                    idx_goal += 1;
-                }
-                let i_goal = goal_order[idx_goal].0;
-                let j_goal = goal_order[idx_goal].1;
-
-                // 目標に最も近いロボットを求める
-                let mut shortest_path_robot = 0;
-                let mut shortest_path_len =
-                    shortest_paths[robots_pos[0].0][robots_pos[0].1][i_goal][j_goal].len();
-                for i in 1..M {
-                    let cur_len =
-                        shortest_paths[robots_pos[i].0][robots_pos[i].1][i_goal][j_goal].len();
-                    if cur_len < shortest_path_len {
-                        shortest_path_robot = i;
-                        shortest_path_len = cur_len;
-                    }
-                }
-                // println!("goal: {:?}", goal_order[idx_goal]);
-                // println!("  from robot[{}]", shortest_path_robot);
-                // println!("  path: {:?}", shortest_paths[robots_pos[shortest_path_robot].0][robots_pos[shortest_path_robot].1][i_goal][j_goal]);
-
-                for cur_op in &shortest_paths[robots_pos[shortest_path_robot].0]
-                    [robots_pos[shortest_path_robot].1][i_goal][j_goal]
-                {
-                    operations.push(op_to_num(*cur_op));
-
-                    // ロボット現在位置の更新
-                    for i in 0..M {
-                        robots_pos[i] = move_pos(robots_pos[i], cur_op.dir(), &vn, &hn);
-                        let ii = robots_pos[i].0;
-                        let jj = robots_pos[i].1;
-                        visited[ii][jj] = true;
-                        unvisited.remove(&(ii, jj));
-                    }
-
-                    turn_current += 1;
-                    if turn_current == TURN_MAX {
-                        // 手数超過により強制終了
-                        break;
-                    }
-                }
-            }
-            // println!("{:?}", unvisited);
-            // println!("{:?}", robots_pos);
-
-            // 記録更新判定
-            let score = if unvisited.is_empty() {
-                3 * N * N - operations.len()
-            } else {
-                N * N - unvisited.len()
-            };
+    let mut ans_score = 0;
+    let mut ans_button = buttons.clone();
+    let mut ans_order = goal_orders[0].clone();
+    let mut button_candidates = vec![buttons];
+
+    let button_search_break_time = break_time.mul_f64(BUTTON_SEARCH_TIME_RATIO);
+    while start_time.elapsed() < button_search_break_time {
+        // 訪問順の候補は現在のボタン割り当てで, ボタン割り当ての候補は現在の訪問順で評価する
+        let candidates = goal_orders
+            .iter()
+            .map(|goal_order| (&ans_button, goal_order))
+            .chain(
+                button_candidates
+                    .iter()
+                    .map(|buttons| (buttons, &ans_order)),
+            )
+            .map(|(buttons, goal_order)| {
+                let sim = Simulation::new(buttons, goal_order, &ijm, &shortest_paths, &vn, &hn);
+                (sim.score, buttons.clone(), goal_order.clone())
+            })
+            .collect::<Vec<_>>();
+
+        // 記録更新判定
+        for (score, buttons, goal_order) in candidates {
             if score > ans_score {
                 ans_score = score;
-                ans_button = buttons.clone();
-                ans_operation = operations.clone();
-                ans_order = goal_order.clone();
+                ans_button = buttons;
+                ans_order = goal_order;
             }
-
-            // TODO: DEBUG
-            // break;
         }
-
-        // 訪問順を適当に並べ替えて登山
-        // TODO: 近傍とか壁沿いとかを入れ替えたほうがよいのだろうが, だるい
+        // 訪問順は後で焼きなますので, 角からの蛇行順は最初に一度だけ試す
         goal_orders.clear();
-        for _ in 0..ORDER_CANDIDATE_NUM {
-            let mut goal_order = ans_order.clone();
-            for _ in 0..rng.gen::<usize>() % SWAP_COUNT_MAX + 1 {
-                let i = rng.gen::<usize>() % goal_order.len();
-                let j = rng.gen::<usize>() % goal_order.len();
-                goal_order.swap(i, j);
+
+        // ボタン割り当てを適当に書き換えて登山
+        // ロボットごとに別の向きへ動かしたり止めたりして, 並行して別の領域を掃除させたい
+        button_candidates.clear();
+        while button_candidates.len() < BUTTON_CANDIDATE_NUM {
+            let mut buttons = ans_button.clone();
+            for _ in 0..rng.gen::<usize>() % BUTTON_CHANGE_COUNT_MAX + 1 {
+                let b = rng.gen::<usize>() % K;
+                let j = rng.gen::<usize>() % M;
+                buttons[b][j] = Operation::ALL[rng.gen::<usize>() % Operation::ALL.len()];
+            }
+            // どのロボットも任意の向きに動かせないと, 最短経路を辿れなくなる
+            if is_controllable(&buttons) {
+                button_candidates.push(buttons);
             }
-            goal_orders.push(goal_order);
         }
     }
 
+    let ans_sim = anneal_goal_order(
+        &ans_button,
+        ans_order,
+        &ijm,
+        &shortest_paths,
+        &vn,
+        &hn,
+        break_time,
+        start_time,
+        &mut rng,
+    );
+
     for ac in ans_button {
         println!("{}", ac.iter().join(" "));
     }
-    for ao in ans_operation {
+    for ao in ans_sim.operations {
         println!("{ao}");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wall_follow_order_covers_board_from_dead_end() {
+        // (0, 0) は下を壁で塞いだ行き止まりで, (0, 1) も下を塞ぐ
+        // (0, 1) から左向きに歩き始めると, 背後の (0, 2) 以外に抜け道がない
+        let vn = vec![vec!['0'; N - 1]; N];
+        let mut hn = vec![vec!['0'; N]; N - 1];
+        hn[0][0] = '1';
+        hn[0][1] = '1';
+        for right_hand in [true, false] {
+            let order = wall_follow_order((0, 1), 3, right_hand, &vn, &hn);
+            assert_eq!(order.len(), N * N);
+        }
+    }
+}
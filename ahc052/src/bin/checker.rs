@@ -0,0 +1,169 @@
+// ボタン割り当てと操作列を再生してスコアと未訪問マスを出力する
+//
+// cargo run -r --bin checker <input> <output>
+
+use std::process::exit;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operation {
+    L,
+    R,
+    U,
+    D,
+    S,
+}
+
+impl std::str::FromStr for Operation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "L" => Ok(Operation::L),
+            "R" => Ok(Operation::R),
+            "U" => Ok(Operation::U),
+            "D" => Ok(Operation::D),
+            "S" => Ok(Operation::S),
+            _ => Err(format!("unknown operation {s:?}")),
+        }
+    }
+}
+
+impl Operation {
+    fn dir(&self) -> (isize, isize) {
+        match self {
+            Operation::L => (0, -1),
+            Operation::R => (0, 1),
+            Operation::U => (-1, 0),
+            Operation::D => (1, 0),
+            Operation::S => (0, 0),
+        }
+    }
+}
+
+/// `a.rs` の `move_pos` と同じ, 盤外や壁に当たるときはその場に留まる
+fn move_pos(
+    vcur: (usize, usize),
+    dij: (isize, isize),
+    vn: &[Vec<char>],
+    hn: &[Vec<char>],
+) -> (usize, usize) {
+    let n = vn.len();
+    let ni = vcur.0.wrapping_add_signed(dij.0);
+    let nj = vcur.1.wrapping_add_signed(dij.1);
+    if ni >= n || nj >= n {
+        return vcur;
+    }
+
+    let is = vcur.0.min(ni);
+    let ib = vcur.0.max(ni);
+    let js = vcur.1.min(nj);
+    let jb = vcur.1.max(nj);
+    if (is != ib && hn[is][js] == '1') || (js != jb && vn[is][js] == '1') {
+        return vcur;
+    }
+
+    (ni, nj)
+}
+
+fn parse_output(
+    output: &str,
+    m: usize,
+    k: usize,
+) -> Result<(Vec<Vec<Operation>>, Vec<usize>), String> {
+    let mut lines = output.lines().filter(|line| !line.trim().is_empty());
+    let mut buttons = vec![];
+    for i in 0..k {
+        let line = lines
+            .next()
+            .ok_or_else(|| format!("button {i} is missing"))?;
+        let button = line
+            .split_whitespace()
+            .map(|tok| tok.parse::<Operation>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("button {i}: {e}"))?;
+        if button.len() != m {
+            return Err(format!("button {i} must have {m} operations"));
+        }
+        buttons.push(button);
+    }
+
+    let mut operations = vec![];
+    for (t, line) in lines.enumerate() {
+        let button = line
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|&b| b < k)
+            .ok_or_else(|| format!("turn {t}: invalid button {line:?}"))?;
+        operations.push(button);
+    }
+
+    Ok((buttons, operations))
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() != 2 {
+        eprintln!("usage: checker <input> <output>");
+        exit(2);
+    }
+
+    let input = std::fs::read_to_string(&args[0]).expect("failed to read input");
+    let mut tokens = input.split_whitespace();
+    let mut next = || tokens.next().expect("input is too short");
+    let n = next().parse::<usize>().expect("failed to parse N");
+    let m = next().parse::<usize>().expect("failed to parse M");
+    let k = next().parse::<usize>().expect("failed to parse K");
+    let ijm = (0..m)
+        .map(|_| {
+            let i = next().parse::<usize>().expect("failed to parse i");
+            let j = next().parse::<usize>().expect("failed to parse j");
+            (i, j)
+        })
+        .collect::<Vec<_>>();
+    let vn = (0..n)
+        .map(|_| next().chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let hn = (0..n - 1)
+        .map(|_| next().chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let output = std::fs::read_to_string(&args[1]).expect("failed to read output");
+    let (buttons, operations) = parse_output(&output, m, k).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        exit(1);
+    });
+    let turn_max = 2 * n * n;
+    if operations.len() > turn_max {
+        eprintln!("too many operations: {} > {turn_max}", operations.len());
+        exit(1);
+    }
+
+    let mut robots_pos = ijm;
+    let mut visited = vec![vec![false; n]; n];
+    for &(i, j) in &robots_pos {
+        visited[i][j] = true;
+    }
+    for &button in &operations {
+        for (pos, op) in robots_pos.iter_mut().zip(&buttons[button]) {
+            *pos = move_pos(*pos, op.dir(), &vn, &hn);
+            visited[pos.0][pos.1] = true;
+        }
+    }
+
+    let unvisited = (0..n)
+        .flat_map(|i| (0..n).map(move |j| (i, j)))
+        .filter(|&(i, j)| !visited[i][j])
+        .collect::<Vec<_>>();
+    let score = if unvisited.is_empty() {
+        3 * n * n - operations.len()
+    } else {
+        n * n - unvisited.len()
+    };
+    println!("Turns = {}", operations.len());
+    println!("Unvisited = {}", unvisited.len());
+    for (i, j) in &unvisited {
+        println!("  ({i}, {j})");
+    }
+    println!("Score = {score}");
+}
@@ -9,7 +9,7 @@ const M: usize = 50;
 const L: i64 = 1_000_000_000_000_000 - 2 * 1_000_000_000_000;
 const U: i64 = 1_000_000_000_000_000 + 2 * 1_000_000_000_000;
 
-// swap 一回であれば差分を取ったほうが高速だが絶対値に注意
+// 振り分け全体の誤差, 一回の移動や交換の差分は `Mountains` で求める
 fn calc_score(an: &[i64], bm: &[i64], distributed_to: &[usize]) -> i64 {
     let mut mountains = vec![0; M];
     for i in 0..N {
@@ -28,7 +28,7 @@ fn calc_score(an: &[i64], bm: &[i64], distributed_to: &[usize]) -> i64 {
     ret
 }
 
-fn _calc_mountains(an: &[i64], distributed_to: &[usize]) -> Vec<i64> {
+fn calc_mountains(an: &[i64], distributed_to: &[usize]) -> Vec<i64> {
     let mut ret = vec![0; M];
     for i in 0..N {
         if distributed_to[i] == 0 {
@@ -40,6 +40,358 @@ fn _calc_mountains(an: &[i64], distributed_to: &[usize]) -> Vec<i64> {
     ret
 }
 
+/// 振り分けと各山の和を保持し, 値の移動と交換による誤差の変化を O(1) で求める
+/// 山の添字は `distributed_to` と同じく 1..=M, 0 は未使用の値を入れる仮の山で誤差に数えない
+struct Mountains<'a> {
+    an: &'a [i64],
+    bm: &'a [i64],
+    distributed_to: Vec<usize>,
+    sums: Vec<i64>,
+    error: i64,
+}
+
+impl<'a> Mountains<'a> {
+    fn new(an: &'a [i64], bm: &'a [i64], distributed_to: Vec<usize>) -> Self {
+        let sums = std::iter::once(0)
+            .chain(calc_mountains(an, &distributed_to))
+            .collect::<Vec<_>>();
+        let error = calc_score(an, bm, &distributed_to);
+        Self {
+            an,
+            bm,
+            distributed_to,
+            sums,
+            error,
+        }
+    }
+
+    fn cost(&self, j: usize, sum: i64) -> i64 {
+        if j == 0 {
+            0
+        } else {
+            (self.bm[j - 1] - sum).abs()
+        }
+    }
+
+    /// 山 `j_from` から山 `j_to` へ和を `shift` だけ移したときの誤差の変化
+    fn shift_delta(&self, j_from: usize, j_to: usize, shift: i64) -> i64 {
+        self.cost(j_from, self.sums[j_from] - shift) - self.cost(j_from, self.sums[j_from])
+            + self.cost(j_to, self.sums[j_to] + shift)
+            - self.cost(j_to, self.sums[j_to])
+    }
+
+    /// i 番目の値を山 `j_to` へ移したときの誤差の変化
+    fn move_delta(&self, i: usize, j_to: usize) -> i64 {
+        let j_from = self.distributed_to[i];
+        if j_from == j_to {
+            return 0;
+        }
+        self.shift_delta(j_from, j_to, self.an[i])
+    }
+
+    /// i 番目と k 番目の値の山を入れ替えたときの誤差の変化
+    fn swap_delta(&self, i: usize, k: usize) -> i64 {
+        let (ji, jk) = (self.distributed_to[i], self.distributed_to[k]);
+        if ji == jk {
+            return 0;
+        }
+        self.shift_delta(ji, jk, self.an[i] - self.an[k])
+    }
+
+    fn apply_move(&mut self, i: usize, j_to: usize) {
+        let j_from = self.distributed_to[i];
+        self.error += self.move_delta(i, j_to);
+        self.sums[j_from] -= self.an[i];
+        self.sums[j_to] += self.an[i];
+        self.distributed_to[i] = j_to;
+    }
+
+    fn apply_swap(&mut self, i: usize, k: usize) {
+        let (ji, jk) = (self.distributed_to[i], self.distributed_to[k]);
+        self.error += self.swap_delta(i, k);
+        let shift = self.an[i] - self.an[k];
+        self.sums[ji] -= shift;
+        self.sums[jk] += shift;
+        self.distributed_to.swap(i, k);
+    }
+}
+
+/// A の構成, 山の土台にする大きな値の段と残差を埋める微調整用の値からなる
+/// 土台は [L, U] を `tier_num` 等分した下端の値で, 各段に B が入る個数の上振れを見込んだ個数を用意する
+/// 微調整用の値は `fine_max` から `fine_min` まで対数軸上に並べる
+/// `fine_shape` が 1 なら等比, 大きいほど大きい値の側に寄せて総和を増やす
+#[derive(Clone, Copy, Debug)]
+struct Design {
+    tier_num: usize,
+    fine_max: f64,
+    fine_min: f64,
+    fine_shape: f64,
+}
+
+impl Design {
+    /// 段ごとの土台の値と個数
+    fn tiers(&self) -> Vec<(i64, usize)> {
+        let spacing = (U - L) / self.tier_num as i64;
+        let p = 1.0 / self.tier_num as f64;
+        let expected = M as f64 * p;
+        // 二項分布の平均 + 3σ
+        let copies = ((expected + 3.0 * (expected * (1.0 - p)).sqrt()).ceil() as usize).min(M);
+        (0..self.tier_num)
+            .map(|k| (L + spacing * k as i64, copies))
+            .collect()
+    }
+
+    /// 土台の値を先頭に, 微調整用の値を降順に並べた A
+    fn build(&self) -> (Vec<i64>, usize) {
+        let mut an = vec![];
+        for (value, copies) in self.tiers() {
+            an.extend(std::iter::repeat_n(value, copies));
+        }
+        let base_num = an.len();
+        let fine_num = N - base_num;
+        let (log_max, log_min) = (self.fine_max.ln(), self.fine_min.ln());
+        an.extend((0..fine_num).map(|i| {
+            let t = (i as f64 / (fine_num - 1) as f64).powf(self.fine_shape);
+            (log_max + (log_min - log_max) * t).exp().round().max(1.0) as i64
+        }));
+
+        (an, base_num)
+    }
+
+    /// [L, U] の一様乱数で作った B に `subset_sum_assign` で振り分けたときの, 誤差の常用対数の平均
+    /// 得点は誤差の対数で決まるため, 誤差そのものではなく対数を平均する
+    fn expected_error(&self, rng: &mut SmallRng, sample_num: usize) -> f64 {
+        let (an, base_num) = self.build();
+        (0..sample_num)
+            .map(|_| {
+                let bm = (0..M).map(|_| rng.gen_range(L..=U)).collect::<Vec<_>>();
+                let ans = subset_sum_assign(&an, &bm, base_num);
+                (1.0 + calc_score(&an, &bm, &ans) as f64).log10()
+            })
+            .sum::<f64>()
+            / sample_num as f64
+    }
+}
+
+/// 段数と微調整用の値の範囲を格子状に試し, 乱数の B に対する誤差の平均が最小の構成を返す
+/// 土台を引いた残差は各山で [0, spacing) の一様分布とみなせるので,
+/// 微調整用の値の総和がその合計の平均 + 3σ に届かない構成は除く
+fn search_design(rng: &mut SmallRng) -> Design {
+    const SAMPLE_NUM: usize = 8;
+
+    let mut best = None;
+    let mut best_error = f64::MAX;
+    for tier_num in [2, 4, 8, 16] {
+        let spacing = (U - L) as f64 / tier_num as f64;
+        let fine_sum_min = spacing * (M as f64 / 2.0 + 3.0 * (M as f64 / 12.0).sqrt());
+        for fine_max_div in [1.0, 2.0, 4.0, 8.0] {
+            for fine_min in [1e5, 1e6, 1e7, 1e8] {
+                for fine_shape in [1.0, 2.0, 3.0] {
+                    let design = Design {
+                        tier_num,
+                        fine_max: spacing / fine_max_div,
+                        fine_min,
+                        fine_shape,
+                    };
+                    let (an, base_num) = design.build();
+                    if (an[base_num..].iter().sum::<i64>() as f64) < fine_sum_min {
+                        continue;
+                    }
+                    let error = design.expected_error(rng, SAMPLE_NUM);
+                    if error < best_error {
+                        best = Some(design);
+                        best_error = error;
+                    }
+                }
+            }
+        }
+    }
+
+    best.unwrap()
+}
+
+/// 土台の割り当て, B の大きい順に, B 以下で最大の未使用の土台を割り当てる (なければ最小の未使用の土台)
+fn assign_bases(an: &[i64], bm: &[i64], base_num: usize) -> Vec<usize> {
+    let mut ans = vec![0; N];
+    let mut targets = (0..M).collect::<Vec<_>>();
+    targets.sort_by_key(|&j| std::cmp::Reverse(bm[j]));
+    for j in targets {
+        let unused = (0..base_num).filter(|&i| ans[i] == 0);
+        let base = unused
+            .clone()
+            .filter(|&i| an[i] <= bm[j])
+            .max_by_key(|&i| an[i])
+            .or_else(|| unused.min_by_key(|&i| an[i]));
+        if let Some(i) = base {
+            ans[i] = j + 1;
+        }
+    }
+
+    ans
+}
+
+/// 各山の B から割り当て済みの値を引いた残差
+fn calc_residuals(an: &[i64], bm: &[i64], ans: &[usize]) -> Vec<i64> {
+    let mut residuals = bm.to_vec();
+    for (&a, &d) in an.iter().zip(ans) {
+        if d != 0 {
+            residuals[d - 1] -= a;
+        }
+    }
+    residuals
+}
+
+/// `values` の部分和のうち `target` に最も近いものを半分全列挙で求め, 選んだ添字のビット集合を返す
+fn closest_subset_sum(values: &[i64], target: i64) -> u64 {
+    let enumerate = |half: &[i64]| {
+        let mut sums = vec![0; 1 << half.len()];
+        for mask in 1..sums.len() {
+            sums[mask] = sums[mask & (mask - 1)] + half[mask.trailing_zeros() as usize];
+        }
+        sums
+    };
+
+    let h = values.len() / 2;
+    let sums_lo = enumerate(&values[..h]);
+    let mut sums_hi = enumerate(&values[h..])
+        .into_iter()
+        .enumerate()
+        .map(|(mask, sum)| (sum, mask))
+        .collect::<Vec<_>>();
+    sums_hi.sort_unstable();
+
+    let mut best = (i64::MAX, 0);
+    for (mask_lo, &sum_lo) in sums_lo.iter().enumerate() {
+        let rest = target - sum_lo;
+        let k = sums_hi.partition_point(|&(sum, _)| sum < rest);
+        // rest をまたぐ前後の二つだけ見ればよい
+        for &(sum_hi, mask_hi) in sums_hi[k.saturating_sub(1)..(k + 1).min(sums_hi.len())].iter() {
+            let diff = (rest - sum_hi).abs();
+            if diff < best.0 {
+                best = (diff, mask_lo as u64 | (mask_hi as u64) << h);
+            }
+        }
+    }
+
+    best.1
+}
+
+/// 山ごとに部分和問題を解く振り分け
+/// 土台を `assign_bases` で割り当て, 微調整用の値を大きい順に, 残差が最大の山へ残差を超えない範囲で入れる
+/// そのあと残差の大きい山から順に, 残差以下の未使用の値のうち大きい方から `POOL_NUM` 個の部分和で残差を最もよく埋める
+fn subset_sum_assign(an: &[i64], bm: &[i64], base_num: usize) -> Vec<usize> {
+    const POOL_NUM: usize = 24;
+
+    let mut ans = assign_bases(an, bm, base_num);
+    let mut residuals = calc_residuals(an, bm, &ans);
+
+    let mut fines = (base_num..N).collect::<Vec<_>>();
+    fines.sort_by_key(|&i| std::cmp::Reverse(an[i]));
+    for &i in &fines {
+        let j = (0..M).max_by_key(|&j| residuals[j]).unwrap();
+        if an[i] <= residuals[j] {
+            ans[i] = j + 1;
+            residuals[j] -= an[i];
+        }
+    }
+
+    let mut targets = (0..M).collect::<Vec<_>>();
+    targets.sort_by_key(|&j| std::cmp::Reverse(residuals[j]));
+    for j in targets {
+        if residuals[j] <= 0 {
+            continue;
+        }
+
+        let pool = fines
+            .iter()
+            .copied()
+            .filter(|&i| ans[i] == 0 && an[i] <= residuals[j])
+            .take(POOL_NUM)
+            .collect::<Vec<_>>();
+        let values = pool.iter().map(|&i| an[i]).collect::<Vec<_>>();
+        let mask = closest_subset_sum(&values, residuals[j]);
+        for (k, &i) in pool.iter().enumerate() {
+            if mask >> k & 1 == 1 {
+                ans[i] = j + 1;
+                residuals[j] -= an[i];
+            }
+        }
+    }
+
+    ans
+}
+
+/// 山の間での値の移動と交換による焼きなまし
+/// 得点は誤差の対数で決まるため, 誤差の常用対数の差で遷移を判定する
+/// 誤差の変化は `Mountains` で O(1) で求める
+fn anneal_assign(
+    an: &[i64],
+    bm: &[i64],
+    ans: Vec<usize>,
+    break_time: Duration,
+    start_time: Instant,
+    rng: &mut SmallRng,
+) -> Vec<usize> {
+    const TEMP_BEGIN: f64 = 0.005;
+    const TEMP_END: f64 = 0.0001;
+
+    let mut mountains = Mountains::new(an, bm, ans);
+    let mut best = (mountains.error, mountains.distributed_to.clone());
+
+    let begin_time = start_time.elapsed();
+    let mut iter = 0;
+    let mut temp = TEMP_BEGIN;
+    loop {
+        // 時刻の取得は重いので間引く
+        if iter % 256 == 0 {
+            if start_time.elapsed() >= break_time {
+                break;
+            }
+            let progress = (start_time.elapsed() - begin_time).as_secs_f64()
+                / (break_time - begin_time).as_secs_f64();
+            temp = TEMP_BEGIN * (TEMP_END / TEMP_BEGIN).powf(progress);
+        }
+        iter += 1;
+
+        let i = rng.gen::<usize>() % N;
+        let is_move = rng.gen::<usize>() % 2 == 0;
+        // 移動なら移動先の山, 交換なら相手の添字
+        let k_or_j = if is_move {
+            rng.gen::<usize>() % (M + 1)
+        } else {
+            rng.gen::<usize>() % N
+        };
+        let j_to = if is_move {
+            k_or_j
+        } else {
+            mountains.distributed_to[k_or_j]
+        };
+        if mountains.distributed_to[i] == j_to {
+            continue;
+        }
+        let delta = if is_move {
+            mountains.move_delta(i, k_or_j)
+        } else {
+            mountains.swap_delta(i, k_or_j)
+        };
+
+        let error = mountains.error;
+        let diff = (1.0 + error as f64).log10() - (1.0 + (error + delta) as f64).log10();
+        if diff >= 0.0 || rng.gen::<f64>() < (diff / temp).exp() {
+            if is_move {
+                mountains.apply_move(i, k_or_j);
+            } else {
+                mountains.apply_swap(i, k_or_j);
+            }
+            if mountains.error < best.0 {
+                best = (mountains.error, mountains.distributed_to.clone());
+            }
+        }
+    }
+
+    best.1
+}
+
 fn get_line() -> String {
     let mut s = String::new();
     std::io::stdin().read_line(&mut s).ok();
@@ -69,31 +421,9 @@ fn main() {
     // 工夫が弱い...
 
     // 初期値が上四桁 0998, 0999, 1000, 1001 の四通りとして
-    // 微調整用の値を足す
-
-    let mut an = vec![(L + U) / 2; N];
-    // 初期値用
-    for i in 0..20 {
-        an[i] = 998_000_000_000_000;
-    }
-    for i in 20..40 {
-        an[i] = 999_000_000_000_000;
-    }
-    for i in 40..60 {
-        an[i] = 1_000_000_000_000_000;
-    }
-    for i in 60..80 {
-        an[i] = 1_001_000_000_000_000;
-    }
-    // 微調整用
-    // floor(((10^12)/2-10^9)/420)/2
-    let d = 1_188_095_238 / 2;
-    for i in 80..N {
-        an[i] = (10_000_000_000 + d * (i - 80)) as i64;
-    }
-    for i in 420..N {
-        an[i] /= 16;
-    }
+    // 微調整用の値を足す, としていたのを, 段数と微調整用の値の範囲を乱数の B で試して決める
+    let design = search_design(&mut rng);
+    let (an, base_num) = design.build();
 
     let an_line = an
         .iter()
@@ -107,82 +437,8 @@ fn main() {
         .split_whitespace()
         .map(|tok| tok.parse::<i64>().expect("failed to parse i64"))
         .collect();
-    let mut ans = vec![0; N];
-    for i in 0..M {
-        // 雑に半分を入れておく
-        ans[i] = i + 1;
-    }
-    let mut score = calc_score(&an, &bm, &ans);
-    // let mut mountains = calc_mountains(&an, &ans);
-
-    // 値が大きい初期値を先に割り当てておく
-    // 加算操作後だと焼きなまししない限りは更新が起らなくなりそうであるため
-    // TODO: 判定が弱く, 更新できるのに更新しない場合がある
-    // TODO: そこそこ複雑, 共通化したい
-    for i in 0..M {
-        if bm[i] > 1_001_000_000_000_000 {
-            for j in 60..80 {
-                if ans[j] == 0 {
-                    for k in 0..N {
-                        if ans[k] == i + 1 {
-                            ans[k] = 0;
-                        }
-                    }
-                    ans[j] = i + 1;
-                }
-            }
-        } else if bm[i] > 1_000_000_000_000_000 {
-            for j in 40..60 {
-                if ans[j] == 0 {
-                    for k in 0..N {
-                        if ans[k] == i + 1 {
-                            ans[k] = 0;
-                        }
-                    }
-                    ans[j] = i + 1;
-                }
-            }
-        } else if bm[i] > 999_000_000_000_000 {
-            for j in 20..40 {
-                if ans[j] == 0 {
-                    for k in 0..N {
-                        if ans[k] == i + 1 {
-                            ans[k] = 0;
-                        }
-                    }
-                    ans[j] = i + 1;
-                }
-            }
-        }
-    }
-
-    while start_time.elapsed() < break_time {
-        let mut ans_cur = ans.clone();
-        let a_i = rng.gen::<usize>() % N;
-
-        if rng.gen::<usize>() % 2 == 0 {
-            // 加算
-            let distribution_i = rng.gen::<usize>() % (M + 1);
-            ans_cur[a_i] = distribution_i;
-            let score_cur = calc_score(&an, &bm, &ans_cur);
-            if score_cur < score {
-                ans = ans_cur;
-                score = score_cur;
-            }
-        } else {
-            // 交換
-            let distribution_i = ans[a_i];
-            let a_j = rng.gen::<usize>() % N;
-            let distribution_j = ans[a_j];
-            ans_cur[a_i] = distribution_j;
-            ans_cur[a_j] = distribution_i;
-            let score_cur = calc_score(&an, &bm, &ans_cur);
-            if score_cur < score {
-                ans = ans_cur;
-                score = score_cur;
-            }
-        }
-    }
+    let ans = subset_sum_assign(&an, &bm, base_num);
+    let ans = anneal_assign(&an, &bm, ans, break_time, start_time, &mut rng);
 
     // 解の出力
     let ans_line = ans
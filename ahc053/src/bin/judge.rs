@@ -0,0 +1,107 @@
+// ローカル用のジャッジ, 解答プログラムを子プロセスとして起動してパイプでやり取りする
+//
+// cargo run -r --bin judge <seed> <solver> [solver args...]
+// 例: cargo run -r --bin judge 0 ../target/release/a
+//
+// 1. N M L U を渡す
+// 2. A (N 個) を受け取る
+// 3. seed から [L, U] の一様乱数で作った B (M 個) を渡す
+// 4. 各 A の振り分け先 (0 は未使用, 1..=M は山の番号) を受け取り, 誤差と得点を出力する
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{exit, Command, Stdio};
+
+const N: usize = 500;
+const M: usize = 50;
+const L: i64 = 1_000_000_000_000_000 - 2 * 1_000_000_000_000;
+const U: i64 = 1_000_000_000_000_000 + 2 * 1_000_000_000_000;
+
+/// `a.rs` の `calc_score` と同じく, 各山の和と B の差の絶対値の総和
+fn calc_error(an: &[i64], bm: &[i64], distributed_to: &[usize]) -> i64 {
+    let mut mountains = vec![0; M];
+    for (&a, &d) in an.iter().zip(distributed_to) {
+        if d != 0 {
+            mountains[d - 1] += a;
+        }
+    }
+
+    bm.iter()
+        .zip(&mountains)
+        .map(|(b, mountain)| (b - mountain).abs())
+        .sum()
+}
+
+fn parse_line<T: std::str::FromStr>(line: &str, len: usize, name: &str) -> Vec<T> {
+    let ret = line
+        .split_whitespace()
+        .map(|tok| tok.parse::<T>().ok())
+        .collect::<Option<Vec<_>>>()
+        .unwrap_or_else(|| {
+            eprintln!("{name}: failed to parse {line:?}");
+            exit(1);
+        });
+    if ret.len() != len {
+        eprintln!("{name}: expected {len} values, got {}", ret.len());
+        exit(1);
+    }
+    ret
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("usage: judge <seed> <solver> [solver args...]");
+        exit(2);
+    }
+    let seed = args[0].parse::<u64>().expect("invalid seed");
+
+    let mut child = Command::new(&args[1])
+        .args(&args[2..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start solver");
+    let mut solver_in = child.stdin.take().unwrap();
+    let mut solver_out = BufReader::new(child.stdout.take().unwrap());
+    let mut read_line = || {
+        let mut s = String::new();
+        solver_out
+            .read_line(&mut s)
+            .expect("failed to read from solver");
+        s
+    };
+
+    writeln!(solver_in, "{N} {M} {L} {U}").unwrap();
+    solver_in.flush().unwrap();
+
+    let an = parse_line::<i64>(&read_line(), N, "A");
+    if let Some(a) = an.iter().find(|&&a| a <= 0) {
+        eprintln!("A: {a} is not positive");
+        exit(1);
+    }
+
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let bm = (0..M).map(|_| rng.gen_range(L..=U)).collect::<Vec<_>>();
+    let bm_line = bm
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+    writeln!(solver_in, "{bm_line}").unwrap();
+    solver_in.flush().unwrap();
+
+    let distributed_to = parse_line::<usize>(&read_line(), N, "assignment");
+    if let Some(d) = distributed_to.iter().find(|&&d| d > M) {
+        eprintln!("assignment: {d} is out of [0, {M}]");
+        exit(1);
+    }
+    child.wait().expect("solver did not exit");
+
+    // 完璧に振り分けられると 1e8 点, 誤差が 10 倍になるごとに 5e6 点下がる
+    let error = calc_error(&an, &bm, &distributed_to);
+    let score = (5e6 * (20.0 - (1.0 + error as f64).log10())).round() as i64;
+    println!("Error = {error}");
+    println!("Score = {score}");
+}
@@ -0,0 +1,246 @@
+// ローカル用のジャッジ, 解答プログラムを子プロセスとして起動してパイプでやり取りする
+//
+// cargo run -r --bin judge <input> <seed> <solver> [solver args...]
+// 例: cargo run -r --bin judge tools/in/0000.txt 0 ../target/release/a
+//
+// 1. 入力ファイルの N, (ti, tj), 盤面を渡す (盤面より後ろの行は読まない)
+// 2. 毎ターン, 冒険者の位置と前のターンで新たに確認済みになったマスを渡し, トレントの配置を受け取る
+// 3. 冒険者は以下の順に行動する
+//    - 四方向に木に遮られるまでのマス (木を含む) を確認済みにする
+//    - 花が確認済みなら花を目的地にする
+//    - 目的地がない, 確認済みになった, 確認済みの木だけを障害物とみなして到達できない, のいずれかなら,
+//      そうみなして到達できる未確認マスから seed の乱数で一様に目的地を選び直す
+//    - 確認済みの木だけを障害物とみなした最短経路で 1 マス進む, 候補が複数あれば上下左右の順に優先する
+// 4. 冒険者が花に着くまでのターン数を出力する
+//
+// 解答が -1 を出力した場合はそれ以降トレントを置かずに最後までシミュレートする.
+// 最初のターンで確認済みなのは入口のマスだけで, 新たに確認済みになったマスは 0 個として渡す.
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{exit, Command, Stdio};
+
+const DXY: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// `is_wall` を障害物として `sxy` からの距離を返す, 到達できないマスは `usize::MAX`
+fn distances(sxy: (usize, usize), is_wall: &[Vec<bool>]) -> Vec<Vec<usize>> {
+    let n = is_wall.len();
+    let mut dist = vec![vec![usize::MAX; n]; n];
+    let mut que = VecDeque::new();
+    dist[sxy.0][sxy.1] = 0;
+    que.push_back(sxy);
+    while let Some((x, y)) = que.pop_front() {
+        for &(dx, dy) in &DXY {
+            let nx = x.wrapping_add_signed(dx);
+            let ny = y.wrapping_add_signed(dy);
+            if nx >= n || ny >= n || is_wall[nx][ny] || dist[nx][ny] != usize::MAX {
+                continue;
+            }
+            dist[nx][ny] = dist[x][y] + 1;
+            que.push_back((nx, ny));
+        }
+    }
+    dist
+}
+
+struct Adventurer {
+    pos: (usize, usize),
+    target: Option<(usize, usize)>,
+    is_found: Vec<Vec<bool>>,
+    /// 確認済みの木, 冒険者はこれだけを障害物とみなす
+    saw_tree: Vec<Vec<bool>>,
+}
+
+impl Adventurer {
+    fn new(n: usize, pos: (usize, usize)) -> Self {
+        let mut is_found = vec![vec![false; n]; n];
+        is_found[pos.0][pos.1] = true;
+        Self {
+            pos,
+            target: None,
+            is_found,
+            saw_tree: vec![vec![false; n]; n],
+        }
+    }
+
+    /// 四方向を確認し, 新たに確認済みになったマスを返す
+    fn look_around(&mut self, has_tree: &[Vec<bool>]) -> Vec<(usize, usize)> {
+        let n = has_tree.len();
+        let mut found = vec![];
+        for &(dx, dy) in &DXY {
+            let (mut x, mut y) = self.pos;
+            loop {
+                x = x.wrapping_add_signed(dx);
+                y = y.wrapping_add_signed(dy);
+                if x >= n || y >= n {
+                    break;
+                }
+                if !self.is_found[x][y] {
+                    self.is_found[x][y] = true;
+                    found.push((x, y));
+                }
+                if has_tree[x][y] {
+                    self.saw_tree[x][y] = true;
+                    break;
+                }
+            }
+        }
+        found
+    }
+
+    fn act(&mut self, has_tree: &[Vec<bool>], tij: (usize, usize), rng: &mut SmallRng) {
+        let n = has_tree.len();
+        let dist_from_pos = distances(self.pos, &self.saw_tree);
+        if self.is_found[tij.0][tij.1] {
+            self.target = Some(tij);
+        } else if self
+            .target
+            .is_none_or(|(x, y)| self.is_found[x][y] || dist_from_pos[x][y] == usize::MAX)
+        {
+            let candidates = (0..n)
+                .flat_map(|x| (0..n).map(move |y| (x, y)))
+                .filter(|&(x, y)| !self.is_found[x][y] && dist_from_pos[x][y] != usize::MAX)
+                .collect::<Vec<_>>();
+            // 花へは必ず到達できるので, 花が未確認であれば候補は空にならない
+            self.target = Some(candidates[rng.gen_range(0..candidates.len())]);
+        }
+
+        let target = self.target.unwrap();
+        let dist_to_target = distances(target, &self.saw_tree);
+        let d = dist_to_target[self.pos.0][self.pos.1];
+        self.pos = DXY
+            .iter()
+            .map(|&(dx, dy)| {
+                (
+                    self.pos.0.wrapping_add_signed(dx),
+                    self.pos.1.wrapping_add_signed(dy),
+                )
+            })
+            .find(|&(x, y)| x < n && y < n && dist_to_target[x][y].wrapping_add(1) == d)
+            .expect("adventurer cannot move");
+    }
+}
+
+/// トレントの配置を検証して盤面に反映する
+fn place_treants(
+    line: &str,
+    adventurer: &Adventurer,
+    tij: (usize, usize),
+    has_tree: &mut [Vec<bool>],
+) -> Result<(), String> {
+    let n = has_tree.len();
+    let tokens = line
+        .split_whitespace()
+        .map(|tok| tok.parse::<usize>().ok())
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| format!("failed to parse {line:?}"))?;
+    let Some((&m, xys)) = tokens.split_first() else {
+        return Err("empty output".to_string());
+    };
+    if xys.len() != 2 * m {
+        return Err(format!("expected {m} cells, got {} numbers", xys.len()));
+    }
+
+    for xy in xys.chunks(2) {
+        let (x, y) = (xy[0], xy[1]);
+        if x >= n || y >= n {
+            return Err(format!("({x}, {y}) is out of the forest"));
+        }
+        if has_tree[x][y] {
+            return Err(format!("({x}, {y}) already has a tree"));
+        }
+        if adventurer.is_found[x][y] {
+            return Err(format!("({x}, {y}) is already confirmed"));
+        }
+        if (x, y) == tij {
+            return Err(format!("({x}, {y}) is the flower"));
+        }
+        has_tree[x][y] = true;
+    }
+
+    if distances(adventurer.pos, has_tree)[tij.0][tij.1] == usize::MAX {
+        return Err("the flower became unreachable".to_string());
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 3 {
+        eprintln!("usage: judge <input> <seed> <solver> [solver args...]");
+        exit(2);
+    }
+
+    let input = std::fs::read_to_string(&args[0]).expect("failed to read input");
+    let mut tokens = input.split_whitespace();
+    let mut next = || tokens.next().expect("input is too short");
+    let n = next().parse::<usize>().expect("failed to parse N");
+    let ti = next().parse::<usize>().expect("failed to parse ti");
+    let tj = next().parse::<usize>().expect("failed to parse tj");
+    let bnn = (0..n).map(|_| next().to_string()).collect::<Vec<_>>();
+    let tij = (ti, tj);
+    let mut has_tree = bnn
+        .iter()
+        .map(|row| row.chars().map(|c| c == 'T').collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let seed = args[1].parse::<u64>().expect("invalid seed");
+    let mut rng = SmallRng::seed_from_u64(seed);
+
+    let mut child = Command::new(&args[2])
+        .args(&args[3..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start solver");
+    let mut solver_in = child.stdin.take().unwrap();
+    let mut solver_out = BufReader::new(child.stdout.take().unwrap());
+
+    writeln!(solver_in, "{n} {ti} {tj}").unwrap();
+    for row in &bnn {
+        writeln!(solver_in, "{row}").unwrap();
+    }
+    solver_in.flush().unwrap();
+
+    let mut adventurer = Adventurer::new(n, (0, n / 2));
+    let mut found = vec![];
+    let mut is_interactive = true;
+    let mut turn = 0;
+    loop {
+        if is_interactive {
+            let found_line = found
+                .iter()
+                .map(|(x, y)| format!(" {x} {y}"))
+                .collect::<String>();
+            writeln!(solver_in, "{} {}", adventurer.pos.0, adventurer.pos.1).unwrap();
+            writeln!(solver_in, "{}{found_line}", found.len()).unwrap();
+            solver_in.flush().unwrap();
+        }
+        if adventurer.pos == tij {
+            break;
+        }
+
+        if is_interactive {
+            let mut line = String::new();
+            solver_out
+                .read_line(&mut line)
+                .expect("failed to read from solver");
+            if line.trim() == "-1" {
+                is_interactive = false;
+            } else if let Err(e) = place_treants(&line, &adventurer, tij, &mut has_tree) {
+                eprintln!("turn {turn}: {e}");
+                exit(1);
+            }
+        }
+
+        found = adventurer.look_around(&has_tree);
+        adventurer.act(&has_tree, tij, &mut rng);
+        turn += 1;
+    }
+    drop(solver_in);
+    child.wait().expect("solver did not exit");
+
+    println!("Turns = {turn}");
+    println!("Score = {turn}");
+}